@@ -0,0 +1,6 @@
+CREATE TABLE DOMAIN_ALIASES(
+    alias_id INT REFERENCES domains(id) PRIMARY KEY,
+    canonical_id INT REFERENCES domains(id) NOT NULL,
+    CONSTRAINT da_not_self CHECK (alias_id <> canonical_id)
+);
+CREATE INDEX domain_aliases_canonical_idx ON domain_aliases (canonical_id);
@@ -0,0 +1,5 @@
+CREATE TABLE SEEDS(
+    domain_id INT REFERENCES domains(id) NOT NULL,
+    network BOOLEAN NOT NULL,
+    CONSTRAINT seeds_pk PRIMARY KEY (domain_id, network)
+);
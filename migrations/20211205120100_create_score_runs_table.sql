@@ -0,0 +1,14 @@
+CREATE TABLE SCORE_RUNS(
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    state TEXT NOT NULL,
+    started_at BIGINT NOT NULL,
+    finished_at BIGINT
+);
+CREATE TABLE DOMAIN_SCORES(
+    run_id INT REFERENCES score_runs(id) ON DELETE CASCADE NOT NULL,
+    domain_id INT REFERENCES domains(id) NOT NULL,
+    score_true REAL NOT NULL,
+    score_false REAL NOT NULL,
+    CONSTRAINT ds_pk PRIMARY KEY (run_id, domain_id)
+);
+CREATE INDEX domain_scores_domain_idx ON domain_scores (domain_id);
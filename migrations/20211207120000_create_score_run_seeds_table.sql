@@ -0,0 +1,6 @@
+CREATE TABLE SCORE_RUN_SEEDS(
+    run_id INT REFERENCES score_runs(id) ON DELETE CASCADE NOT NULL,
+    domain_id INT REFERENCES domains(id) NOT NULL,
+    network BOOLEAN NOT NULL,
+    CONSTRAINT srs_pk PRIMARY KEY (run_id, domain_id, network)
+);
@@ -0,0 +1,13 @@
+CREATE TABLE PREFIX_LISTS(
+    run_id INT PRIMARY KEY REFERENCES score_runs(id) ON DELETE CASCADE,
+    prefix_length INT NOT NULL,
+    bands REAL[] NOT NULL,
+    published_at BIGINT NOT NULL
+);
+CREATE TABLE PREFIX_LIST_ENTRIES(
+    run_id INT REFERENCES prefix_lists(run_id) ON DELETE CASCADE NOT NULL,
+    prefix TEXT NOT NULL,
+    band_true SMALLINT NOT NULL,
+    band_false SMALLINT NOT NULL,
+    CONSTRAINT ple_pk PRIMARY KEY (run_id, prefix)
+);
@@ -0,0 +1,12 @@
+CREATE TABLE CRAWL_SESSIONS(
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    fqdn TEXT NOT NULL,
+    network BOOLEAN NOT NULL,
+    last_updated BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+CREATE TABLE CRAWL_SESSION_LINKS(
+    session_id INT REFERENCES crawl_sessions(id) ON DELETE CASCADE NOT NULL,
+    fqdn TEXT NOT NULL,
+    CONSTRAINT csl_pk PRIMARY KEY (session_id, fqdn)
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IMPORTS(
+    file TEXT PRIMARY KEY,
+    line BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
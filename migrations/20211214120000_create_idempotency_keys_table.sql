@@ -0,0 +1,8 @@
+CREATE TABLE IDEMPOTENCY_KEYS(
+    key TEXT PRIMARY KEY,
+    fingerprint TEXT NOT NULL,
+    status SMALLINT,
+    body TEXT,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
@@ -0,0 +1,12 @@
+-- domains are crawled once they were submitted as source, linked domains stay uncrawled until then
+ALTER TABLE domains
+ADD crawled BOOLEAN NOT NULL DEFAULT false;
+UPDATE domains SET crawled = true WHERE id IN (SELECT source_id FROM domain_link);
+CREATE TABLE CRAWL_FRONTIER(
+    domain_id INT REFERENCES domains(id) ON DELETE CASCADE PRIMARY KEY,
+    priority SMALLINT NOT NULL,
+    reason TEXT NOT NULL,
+    queued_at BIGINT NOT NULL,
+    leased_until BIGINT
+);
+CREATE INDEX crawl_frontier_queue_idx ON crawl_frontier (priority DESC, queued_at);
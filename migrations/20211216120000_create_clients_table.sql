@@ -0,0 +1,30 @@
+CREATE TABLE CLIENTS(
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    token_hash TEXT UNIQUE NOT NULL,
+    created_at BIGINT NOT NULL
+);
+CREATE TABLE ROLES(
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL
+);
+INSERT INTO roles (name) VALUES ('scraper');
+CREATE TABLE CLIENT_ROLES(
+    client_id INT REFERENCES clients(id) ON DELETE CASCADE NOT NULL,
+    role_id INT REFERENCES roles(id) ON DELETE CASCADE NOT NULL,
+    CONSTRAINT cr_pk PRIMARY KEY (client_id, role_id)
+);
+-- provenance of each link, links existing before scrapers were registered aren't attributed
+ALTER TABLE domain_link
+ADD scraper_id INT REFERENCES clients(id),
+ADD crawled_at BIGINT;
+CREATE INDEX domain_link_scraper_idx ON domain_link (scraper_id, crawled_at);
+CREATE TABLE QUARANTINED_LINKS(
+    source_id INT REFERENCES domains(id) ON DELETE CASCADE NOT NULL,
+    target_id INT REFERENCES domains(id) ON DELETE CASCADE NOT NULL,
+    scraper_id INT REFERENCES clients(id),
+    crawled_at BIGINT,
+    quarantined_at BIGINT NOT NULL
+);
+ALTER TABLE crawl_sessions
+ADD scraper_id INT REFERENCES clients(id);
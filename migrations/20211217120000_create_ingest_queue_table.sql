@@ -0,0 +1,8 @@
+-- durable backing of the ingestion queue, only used if `ingest.queue_durable` is enabled
+CREATE TABLE INGEST_QUEUE(
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    payload TEXT NOT NULL,
+    scraper_id INT REFERENCES clients(id) ON DELETE SET NULL,
+    enqueued_at BIGINT NOT NULL,
+    leased_until BIGINT
+);
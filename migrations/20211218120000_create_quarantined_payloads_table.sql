@@ -0,0 +1,11 @@
+-- payloads rejected by the ingestion endpoints, kept for debugging scrapers
+CREATE TABLE QUARANTINED_PAYLOADS(
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    payload TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    loc TEXT,
+    scraper_id INT REFERENCES clients(id) ON DELETE SET NULL,
+    source TEXT NOT NULL,
+    quarantined_at BIGINT NOT NULL
+);
+CREATE INDEX quarantined_payloads_scraper_idx ON quarantined_payloads (scraper_id, id);
@@ -0,0 +1,7 @@
+-- keys are scoped by the submitting scraper, anonymous submissions share the scope 0.
+-- Claims expire after `leased_until`, so a request that crashed mid-flight can be retried
+ALTER TABLE idempotency_keys
+DROP CONSTRAINT idempotency_keys_pkey,
+ADD scraper_id INT REFERENCES clients(id) ON DELETE CASCADE,
+ADD leased_until BIGINT NOT NULL DEFAULT 0;
+CREATE UNIQUE INDEX idempotency_keys_scope_idx ON idempotency_keys (coalesce(scraper_id, 0), key);
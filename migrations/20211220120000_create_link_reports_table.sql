@@ -0,0 +1,25 @@
+-- provenance of each link per reporting scraper, anonymous and imported reports share the scraper `null`.
+-- Reports are recorded with the time the server received them, links existing before scrapers were
+-- registered aren't attributed
+CREATE TABLE LINK_REPORTS(
+    source_id INT NOT NULL,
+    target_id INT NOT NULL,
+    scraper_id INT REFERENCES clients(id) ON DELETE CASCADE,
+    first_reported_at BIGINT NOT NULL,
+    last_reported_at BIGINT NOT NULL,
+    CONSTRAINT lr_link_fk FOREIGN KEY (source_id, target_id)
+        REFERENCES domain_link (source_id, target_id) ON DELETE CASCADE
+);
+CREATE UNIQUE INDEX link_reports_scope_idx ON link_reports (source_id, target_id, coalesce(scraper_id, 0));
+CREATE INDEX link_reports_scraper_idx ON link_reports (scraper_id, last_reported_at);
+-- the last report of each link is the only one known, crawl times are the closest to a receive time
+INSERT INTO link_reports (source_id, target_id, scraper_id, first_reported_at, last_reported_at)
+SELECT source_id, target_id, scraper_id, crawled_at, crawled_at
+FROM domain_link
+WHERE crawled_at IS NOT NULL;
+DROP INDEX domain_link_scraper_idx;
+ALTER TABLE domain_link
+DROP COLUMN scraper_id,
+DROP COLUMN crawled_at;
+ALTER TABLE quarantined_links
+RENAME COLUMN crawled_at TO reported_at;
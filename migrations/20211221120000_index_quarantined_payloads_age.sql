@@ -0,0 +1,2 @@
+-- quarantined payloads are discarded after `quarantine.retention`
+CREATE INDEX quarantined_payloads_age_idx ON quarantined_payloads (quarantined_at);
@@ -0,0 +1,5 @@
+-- running runs store a heartbeat, so a starting instance only fails the runs of stopped instances
+ALTER TABLE score_runs ADD COLUMN heartbeat_at BIGINT;
+UPDATE score_runs SET heartbeat_at = coalesce(finished_at, started_at);
+ALTER TABLE score_runs ALTER COLUMN heartbeat_at SET NOT NULL;
+CREATE INDEX score_runs_state_idx ON score_runs (state, heartbeat_at);
@@ -0,0 +1,53 @@
+use crate::core::config::CONFIG;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+/// # SeedRequest
+/// Request sent by editors to add a domain to the seed set of a network.
+pub struct SeedRequest {
+    pub fqdn: String,
+    pub network: bool,
+}
+
+#[derive(Deserialize, Debug)]
+/// # DiffRequest
+/// Query for comparing the scores of two scoring runs.
+pub struct DiffRequest {
+    pub from: i32,
+    pub to: i32,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+/// # ScraperRequest
+/// Request for registering a scraper, the name has to be unique.
+pub struct ScraperRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+/// # RollbackQuery
+/// Query for withdrawing the links a scraper reported within `from..=to`, as received by the server.
+/// Links also reported by other scrapers are kept.
+pub struct RollbackQuery {
+    pub from: i64,
+    pub to: i64,
+    /// keep the deleted links in the quarantine for later inspection
+    #[serde(default)]
+    pub quarantine: bool,
+}
+
+#[derive(Deserialize, Debug)]
+/// # QuarantineQuery
+/// Query for listing quarantined payloads, optionally only the ones of a single scraper.
+pub struct QuarantineQuery {
+    pub scraper_id: Option<i32>,
+    #[serde(default)]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+}
+
+fn default_per_page() -> u32 {
+    CONFIG.database.domains.per_page
+}
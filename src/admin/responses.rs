@@ -0,0 +1,120 @@
+use crate::core::errors::APIError;
+use crate::db::models::{Client, QuarantinedPayload, ScoreRun};
+use crate::scoring::runner::RunHandle;
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+
+#[derive(Serialize)]
+pub struct RunProgressResponse {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize)]
+pub struct ScoreRunResponse {
+    pub id: i32,
+    pub state: String,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<RunProgressResponse>,
+}
+
+impl ScoreRunResponse {
+    pub fn new(run: ScoreRun, current: Option<RunHandle>) -> Self {
+        let progress = current
+            .filter(|handle| handle.run_id == Some(run.id))
+            .map(|handle| RunProgressResponse {
+                processed: handle.progress.processed.load(Ordering::Relaxed),
+                total: handle.progress.total.load(Ordering::Relaxed),
+            });
+
+        Self {
+            id: run.id,
+            state: run.state,
+            started_at: run.started_at,
+            finished_at: run.finished_at,
+            progress,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScraperResponse {
+    pub id: i32,
+    pub name: String,
+    pub created_at: i64,
+    /// token of a newly registered scraper, it is only stored hashed and can't be retrieved again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl ScraperResponse {
+    pub fn new(client: Client, token: Option<String>) -> Self {
+        Self {
+            id: client.id,
+            name: client.name,
+            created_at: client.created_at,
+            token,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RollbackResponse {
+    /// count of deleted links
+    pub links: u64,
+}
+
+#[derive(Serialize)]
+pub struct QuarantinedPayloadResponse {
+    pub id: i32,
+    pub payload: String,
+    pub reason: String,
+    pub loc: Option<String>,
+    pub scraper_id: Option<i32>,
+    pub source: String,
+    pub quarantined_at: i64,
+}
+
+impl From<QuarantinedPayload> for QuarantinedPayloadResponse {
+    fn from(quarantined: QuarantinedPayload) -> Self {
+        Self {
+            id: quarantined.id,
+            payload: quarantined.payload,
+            reason: quarantined.reason,
+            loc: quarantined.loc,
+            scraper_id: quarantined.scraper_id,
+            source: quarantined.source,
+            quarantined_at: quarantined.quarantined_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RevalidationResponse {
+    /// whether the payload passes the current validation rules
+    pub valid: bool,
+    pub loc: Option<&'static str>,
+    pub msg: Option<String>,
+}
+
+impl From<&Result<(), APIError>> for RevalidationResponse {
+    fn from(result: &Result<(), APIError>) -> Self {
+        match result {
+            Ok(()) => Self {
+                valid: true,
+                loc: None,
+                msg: None,
+            },
+            Err(e) => Self {
+                valid: false,
+                loc: match e {
+                    APIError::ValidationError([loc], _) => Some(*loc),
+                    _ => None,
+                },
+                msg: Some(String::from(e)),
+            },
+        }
+    }
+}
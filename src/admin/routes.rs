@@ -0,0 +1,210 @@
+use super::requests::{DiffRequest, QuarantineQuery, RollbackQuery, ScraperRequest, SeedRequest};
+use super::responses::{
+    QuarantinedPayloadResponse, RevalidationResponse, RollbackResponse, ScoreRunResponse,
+    ScraperResponse,
+};
+use super::util::Admin;
+use crate::core::auth::{token_hash, SCRAPER_ROLE};
+use crate::core::errors::APIError;
+use crate::core::types::APIResponse;
+use crate::db::models::{Client, DomainLink, QuarantinedPayload, ScoreRun, Seed, SimpleDomain};
+use crate::db::util::DbPool;
+use crate::domains::quarantine;
+use crate::domains::queue::IngestQueue;
+use crate::domains::routes::submit_update;
+use crate::domains::util::validate_per_page;
+use crate::scoring::{diff, runner::ScoringRunner};
+use actix_web::{
+    delete, get, post,
+    web::{Data, Json, Path, Query, ServiceConfig},
+    HttpResponse,
+};
+use rand::{distributions::Alphanumeric, Rng};
+
+#[post("/scoring/runs")]
+async fn start_run(_admin: Admin, runner: Data<ScoringRunner>, pool: Data<DbPool>) -> APIResponse {
+    let run = ScoringRunner::start(runner.clone(), pool.as_ref().clone()).await?;
+
+    Ok(HttpResponse::Accepted().json(ScoreRunResponse::new(run, runner.current())))
+}
+
+#[get("/scoring/runs/{id}")]
+async fn get_run(
+    _admin: Admin,
+    id: Path<i32>,
+    runner: Data<ScoringRunner>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    let run = ScoreRun::get(pool.as_ref(), id.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(ScoreRunResponse::new(run, runner.current())))
+}
+
+#[delete("/scoring/runs/{id}")]
+async fn cancel_run(_admin: Admin, id: Path<i32>, runner: Data<ScoringRunner>) -> APIResponse {
+    runner.cancel(id.into_inner())?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+#[get("/scoring/diff")]
+async fn diff_runs(_admin: Admin, query: Query<DiffRequest>, pool: Data<DbPool>) -> APIResponse {
+    let limit = query.limit.unwrap_or(diff::DEFAULT_LIMIT);
+    if !(1..=diff::MAX_LIMIT).contains(&limit) {
+        return Err(APIError::ValidationError(
+            ["limit"],
+            format!("limit has to be between 1 and {}", diff::MAX_LIMIT),
+        ));
+    }
+
+    let report = diff::report(pool.as_ref(), query.from, query.to, limit).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[post("/seeds")]
+async fn add_seed(_admin: Admin, data: Json<SeedRequest>, pool: Data<DbPool>) -> APIResponse {
+    let domain = SimpleDomain::get_by_fqdn(pool.as_ref(), &data.fqdn).await?;
+    Seed::create(pool.as_ref(), domain.id, data.network).await?;
+
+    Ok(HttpResponse::Created().finish())
+}
+
+#[post("/scrapers")]
+async fn add_scraper(_admin: Admin, data: Json<ScraperRequest>, pool: Data<DbPool>) -> APIResponse {
+    if data.name.trim().is_empty() {
+        return Err(APIError::ValidationError(
+            ["name"],
+            "The scraper name must not be empty".to_owned(),
+        ));
+    }
+
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect();
+    let client = Client::create(pool.as_ref(), &data.name, &token_hash(&token), SCRAPER_ROLE)
+        .await
+        .map_err(|e| match e {
+            APIError::IntegrityError => {
+                APIError::ConflictError(format!("A scraper named {} already exists", data.name))
+            }
+            e => e,
+        })?;
+
+    Ok(HttpResponse::Created().json(ScraperResponse::new(client, Some(token))))
+}
+
+#[get("/scrapers")]
+async fn list_scrapers(_admin: Admin, pool: Data<DbPool>) -> APIResponse {
+    let scrapers: Vec<ScraperResponse> = Client::list(pool.as_ref(), SCRAPER_ROLE)
+        .await?
+        .into_iter()
+        .map(|client| ScraperResponse::new(client, None))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(scrapers))
+}
+
+/// Roll back the links a misbehaving scraper reported within a time range, see [`DomainLink::rollback`]
+#[delete("/scrapers/{id}/links")]
+async fn rollback_links(
+    _admin: Admin,
+    id: Path<i32>,
+    query: Query<RollbackQuery>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    if query.from > query.to {
+        return Err(APIError::ValidationError(
+            ["from"],
+            "The start of the range must not be after its end".to_owned(),
+        ));
+    }
+
+    let links = DomainLink::rollback(
+        pool.as_ref(),
+        id.into_inner(),
+        query.from,
+        query.to,
+        query.quarantine,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(RollbackResponse { links }))
+}
+
+#[get("/quarantine")]
+async fn list_quarantine(
+    _admin: Admin,
+    query: Query<QuarantineQuery>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    validate_per_page(query.per_page)?;
+
+    let quarantined: Vec<QuarantinedPayloadResponse> =
+        QuarantinedPayload::list(pool.as_ref(), query.scraper_id, query.per_page, query.page)
+            .await?
+            .into_iter()
+            .map(QuarantinedPayloadResponse::from)
+            .collect();
+
+    Ok(HttpResponse::Ok().json(quarantined))
+}
+
+/// Check a quarantined payload against the current validation rules, e.g. after they were fixed
+#[post("/quarantine/{id}/validate")]
+async fn revalidate_quarantined(_admin: Admin, id: Path<i32>, pool: Data<DbPool>) -> APIResponse {
+    let quarantined = QuarantinedPayload::get(pool.as_ref(), id.into_inner()).await?;
+    let result = quarantine::revalidate(pool.as_ref(), &quarantined)
+        .await?
+        .map(|_| ());
+
+    Ok(HttpResponse::Ok().json(RevalidationResponse::from(&result)))
+}
+
+/// Submit a quarantined payload again as its scraper did, it is discarded once accepted
+#[post("/quarantine/{id}/replay")]
+async fn replay_quarantined(
+    _admin: Admin,
+    id: Path<i32>,
+    queue: Data<IngestQueue>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    let quarantined = QuarantinedPayload::get(pool.as_ref(), id.into_inner()).await?;
+    let update = quarantine::revalidate(pool.as_ref(), &quarantined).await??;
+
+    let response = submit_update(pool.as_ref(), &queue, &update, quarantined.scraper_id).await;
+    match &response {
+        Ok(_) => QuarantinedPayload::delete(pool.as_ref(), quarantined.id).await?,
+        Err(e) if quarantine::quarantines(e) => {
+            QuarantinedPayload::update_reason(pool.as_ref(), quarantined.id, &String::from(e), None)
+                .await?
+        }
+        Err(_) => {}
+    }
+
+    response
+}
+
+#[delete("/quarantine/{id}")]
+async fn discard_quarantined(_admin: Admin, id: Path<i32>, pool: Data<DbPool>) -> APIResponse {
+    QuarantinedPayload::delete(pool.as_ref(), id.into_inner()).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub fn services(cfg: &mut ServiceConfig) {
+    cfg.service(start_run);
+    cfg.service(get_run);
+    cfg.service(cancel_run);
+    cfg.service(diff_runs);
+    cfg.service(add_seed);
+    cfg.service(add_scraper);
+    cfg.service(list_scrapers);
+    cfg.service(rollback_links);
+    cfg.service(list_quarantine);
+    cfg.service(revalidate_quarantined);
+    cfg.service(replay_quarantined);
+    cfg.service(discard_quarantined);
+}
@@ -0,0 +1,32 @@
+use crate::core::{config::CONFIG, errors::APIError};
+use actix_web::{dev::Payload, http::header, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+
+/// Extractor guarding admin routes. Requests have to supply the configured admin token as
+/// `Authorization: Bearer <token>` header
+pub struct Admin;
+
+impl FromRequest for Admin {
+    type Error = APIError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // blake3 hashes are compared in constant time, which avoids leaking the token by timing
+        let authorized = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .zip(CONFIG.admin.token.as_ref())
+            .is_some_and(|(token, admin_token)| {
+                blake3::hash(token.as_bytes()) == blake3::hash(admin_token.as_bytes())
+            });
+
+        ready(match authorized {
+            true => Ok(Admin),
+            false => Err(APIError::AuthError(
+                "Missing or invalid admin token".to_owned(),
+            )),
+        })
+    }
+}
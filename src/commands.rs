@@ -0,0 +1,41 @@
+use crate::db;
+use std::io;
+
+pub mod diff;
+pub mod import;
+
+const USAGE: &str = "Usage: web-of-trust-backend [COMMAND]
+
+Starts the HTTP server when no command is given.
+
+Commands:
+    diff <from_run> <to_run> [limit]    Compare the scores of two scoring runs
+    import <file> [--restart]           Import updates from a JSONL or CSV edge list file,
+                                        resuming after the last imported line";
+
+/// Execute a command given on the command line instead of starting the HTTP server
+pub async fn run(command: &str, args: &[String]) -> io::Result<()> {
+    match command {
+        "diff" => diff::run(&db::util::init_pool().await, args).await,
+        "import" => import::run(&db::util::init_pool().await, args).await,
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => {
+            eprintln!("Unknown command {}\n\n{}", command, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Parse a positional argument or exit with the usage
+pub fn argument<T: std::str::FromStr>(args: &[String], index: usize, name: &str) -> T {
+    match args.get(index).map(|arg| arg.parse()) {
+        Some(Ok(value)) => value,
+        _ => {
+            eprintln!("Missing or invalid argument <{}>\n\n{}", name, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
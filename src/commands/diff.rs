@@ -0,0 +1,59 @@
+use super::argument;
+use crate::db::util::DbPool;
+use crate::scoring::diff::{report, Cause, DEFAULT_LIMIT};
+use std::io;
+
+pub async fn run(pool: &DbPool, args: &[String]) -> io::Result<()> {
+    let from: i32 = argument(args, 0, "from_run");
+    let to: i32 = argument(args, 1, "to_run");
+    let limit: usize = match args.get(2) {
+        Some(_) => argument(args, 2, "limit"),
+        None => DEFAULT_LIMIT,
+    };
+
+    let report = report(pool, from, to, limit)
+        .await
+        .map_err(|e| io::Error::other(String::from(e)))?;
+
+    println!(
+        "Score diff between run {} and run {}",
+        report.from_run, report.to_run
+    );
+    for network in report.networks {
+        println!(
+            "\nNetwork {} (kendall tau {:.4})",
+            network.network, network.kendall_tau
+        );
+
+        for (title, movers) in [("Risers", network.risers), ("Fallers", network.fallers)] {
+            println!("  {}:", title);
+
+            for mover in movers {
+                println!(
+                    "    {:+.4} {} ({:.4} -> {:.4})",
+                    mover.delta, mover.fqdn, mover.from, mover.to
+                );
+
+                for cause in mover.causes {
+                    match cause {
+                        Cause::SeedAdded => println!("        added as seed"),
+                        Cause::SeedRemoved => println!("        removed as seed"),
+                        Cause::Referrer {
+                            fqdn,
+                            contribution_delta,
+                            seed_changed,
+                            ..
+                        } => println!(
+                            "        {:+.4} via {}{}",
+                            contribution_delta,
+                            fqdn,
+                            if seed_changed { " (seed changed)" } else { "" }
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
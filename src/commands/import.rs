@@ -0,0 +1,154 @@
+use super::argument;
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::db::models::Import;
+use crate::db::util::DbPool;
+use crate::domains::requests::UpdateRequest;
+use crate::domains::util::validate_update;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+
+/// Format of an import file, detected by its extension
+pub enum Format {
+    /// a single `UpdateRequest` per line, like the bulk ingestion endpoint
+    Jsonl,
+    /// edge list with a single link per row: `fqdn,network,last_updated[,link]`
+    Csv,
+}
+
+/// Import a file of updates straight into the database, e.g. crawl dumps of partner teams.
+///
+/// Lines are validated like `/update` and loaded in batches of `ingest.import_batch_lines`.
+/// The last line of each loaded batch is recorded, so an interrupted import resumes right after
+/// it. `--restart` imports the file from the start again.
+pub async fn run(pool: &DbPool, args: &[String]) -> io::Result<()> {
+    let path: String = argument(args, 0, "file");
+    let restart = args[1..].iter().any(|arg| arg == "--restart");
+    let format = if path.ends_with(".csv") {
+        Format::Csv
+    } else {
+        Format::Jsonl
+    };
+
+    // progress is tracked by the absolute path of the file
+    let file = fs::canonicalize(&path)?.to_string_lossy().into_owned();
+    let resume = if restart {
+        0
+    } else {
+        Import::progress(pool, &file).await.map_err(io_error)?
+    };
+    if resume > 0 {
+        println!("Resuming import of {} after line {}", file, resume);
+    }
+
+    let mut batch = Vec::with_capacity(CONFIG.ingest.import_batch_lines);
+    let (mut lines, mut imported, mut rejected) = (resume, 0, 0);
+
+    for (index, line) in BufReader::new(File::open(&file)?).lines().enumerate() {
+        let number = index as i64 + 1;
+        if number <= resume {
+            continue;
+        }
+        lines = number;
+
+        match parse(&format, &line?, number) {
+            Ok(Some(record)) => batch.push(record),
+            Ok(None) => {}
+            Err(e) => {
+                rejected += 1;
+                eprintln!("Rejected line {}: {}", number, String::from(e));
+            }
+        }
+
+        if batch.len() >= CONFIG.ingest.import_batch_lines {
+            imported += load(pool, &file, lines, &mut batch).await?;
+            println!(
+                "Imported {} records up to line {}, {} lines rejected",
+                imported, lines, rejected
+            );
+        }
+    }
+    imported += load(pool, &file, lines, &mut batch).await?;
+
+    println!(
+        "Finished import of {}: {} records imported, {} lines rejected",
+        file, imported, rejected
+    );
+
+    Ok(())
+}
+
+/// Load a batch and record `line` as imported. Returns the count of loaded records
+async fn load(
+    pool: &DbPool,
+    file: &str,
+    line: i64,
+    batch: &mut Vec<UpdateRequest>,
+) -> io::Result<usize> {
+    let count = batch.len();
+
+    // loading a batch again is harmless, so an interruption before the progress was recorded
+    // just loads the batch twice
+    Import::load(pool, batch).await.map_err(io_error)?;
+    Import::advance(pool, file, line).await.map_err(io_error)?;
+
+    batch.clear();
+    Ok(count)
+}
+
+/// Parse and validate a line, `None` for empty lines and the CSV header
+pub fn parse(format: &Format, line: &str, number: i64) -> Result<Option<UpdateRequest>, APIError> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let record = match format {
+        Format::Jsonl => serde_json::from_str::<UpdateRequest>(line)
+            .map_err(|e| APIError::ValidationError(["line"], e.to_string()))?,
+        Format::Csv if number == 1 && line.starts_with("fqdn,") => return Ok(None),
+        Format::Csv => parse_csv(line)?,
+    };
+    validate_update(&record)?;
+
+    Ok(Some(record))
+}
+
+/// Parse a row of a CSV edge list. FQDNs can't contain commas or quotes, so rows don't need to be unquoted
+pub fn parse_csv(line: &str) -> Result<UpdateRequest, APIError> {
+    let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+
+    let (fqdn, network, last_updated, link) = match columns.as_slice() {
+        [fqdn, network, last_updated] => (fqdn, network, last_updated, None),
+        [fqdn, network, last_updated, link] => (fqdn, network, last_updated, Some(link)),
+        _ => {
+            return Err(APIError::ValidationError(
+                ["line"],
+                "Expected the columns fqdn,network,last_updated[,link]".to_owned(),
+            ))
+        }
+    };
+
+    Ok(UpdateRequest {
+        fqdn: fqdn.to_string(),
+        network: network.parse().map_err(|_| {
+            APIError::ValidationError(["network"], "network has to be true or false".to_owned())
+        })?,
+        last_updated: last_updated.parse().map_err(|_| {
+            APIError::ValidationError(
+                ["last_updated"],
+                "last_updated has to be a unix timestamp".to_owned(),
+            )
+        })?,
+        links: link
+            .filter(|link| !link.is_empty())
+            .map(|link| link.to_string())
+            .into_iter()
+            .collect(),
+        canonical: None,
+    })
+}
+
+fn io_error(e: APIError) -> io::Error {
+    io::Error::other(String::from(e))
+}
@@ -1,4 +1,9 @@
+pub mod auth;
+pub mod caching;
 pub mod config;
 pub mod errors;
+pub mod idempotency;
+pub mod limits;
 pub mod routes;
 pub mod types;
+pub mod util;
@@ -0,0 +1,56 @@
+use super::config::CONFIG;
+use super::errors::APIError;
+use crate::db::models::Client;
+use crate::db::util::DbPool;
+use actix_web::{dev::Payload, http::header, web::Data, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+
+/// Role granted to the clients registered as scrapers
+pub const SCRAPER_ROLE: &str = "scraper";
+
+/// Hash of a client token as stored in `clients.token_hash`
+pub fn token_hash(token: &str) -> String {
+    blake3::hash(token.as_bytes()).to_hex().to_string()
+}
+
+/// Extractor identifying the scraper submitting crawl results by its
+/// `Authorization: Bearer <token>` header. Requests without a token are attributed to no scraper,
+/// as long as `scrapers.anonymous` allows them.
+pub struct Scraper(pub Option<Client>);
+
+impl Scraper {
+    pub fn id(&self) -> Option<i32> {
+        self.0.as_ref().map(|client| client.id)
+    }
+}
+
+impl FromRequest for Scraper {
+    type Error = APIError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(token_hash);
+        let pool = req.app_data::<Data<DbPool>>().cloned();
+
+        Box::pin(async move {
+            let token = match token {
+                Some(token) => token,
+                None if CONFIG.scrapers.anonymous => return Ok(Scraper(None)),
+                None => return Err(APIError::AuthError("Missing scraper token".to_owned())),
+            };
+            let pool = pool.ok_or_else(|| {
+                APIError::InternalError("Database pool isn't configured".to_owned())
+            })?;
+
+            match Client::by_token(pool.as_ref(), &token, SCRAPER_ROLE).await? {
+                Some(client) => Ok(Scraper(Some(client))),
+                None => Err(APIError::AuthError("Invalid scraper token".to_owned())),
+            }
+        })
+    }
+}
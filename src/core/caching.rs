@@ -0,0 +1,70 @@
+use super::config::CONFIG;
+use super::util::timestamp;
+use actix_web::http::header::{self, EntityTag, Header, HeaderValue, IfNoneMatch};
+use actix_web::{HttpRequest, HttpResponse};
+
+/// HTTP caching of responses that only change once a scoring run is published.
+///
+/// The ETag is derived from the version of the scoring run and everything else the response
+/// depends on, e.g. the prefix, pagination and negotiated format. Responses are cached until the next run is
+/// expected and revalidated afterwards. New domains inserted by scrapers in the meantime don't
+/// change the ETag, hence it is weak.
+pub struct Cacheable {
+    etag: EntityTag,
+    cache_control: String,
+}
+
+impl Cacheable {
+    /// `version` is the id of the scoring run the response is based on, `published_at` the unix
+    /// timestamp it was published at, if any run was published yet
+    pub fn new(version: i32, published_at: Option<i64>, parts: &[&str]) -> Self {
+        let hash = blake3::hash(parts.join("\0").as_bytes()).to_hex();
+        let etag = EntityTag::weak(format!("{}-{}", version, &hash[..16]));
+
+        let now = timestamp();
+        let next_run =
+            published_at.map(|published_at| published_at + CONFIG.scoring.interval as i64);
+        let cache_control = match next_run {
+            Some(next_run) if next_run > now => format!("public, max-age={}", next_run - now),
+            // the next run is overdue and may be published any time
+            _ => "public, no-cache".to_owned(),
+        };
+
+        Self {
+            etag,
+            cache_control,
+        }
+    }
+
+    /// `304 Not Modified` if the client already has the current response
+    pub fn not_modified(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        let cached = match IfNoneMatch::parse(req) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&self.etag)),
+            Err(_) => false,
+        };
+
+        if cached {
+            Some(self.apply(HttpResponse::NotModified().finish()))
+        } else {
+            None
+        }
+    }
+
+    /// Add the caching headers to a response
+    pub fn apply(&self, mut response: HttpResponse) -> HttpResponse {
+        let headers = response.headers_mut();
+
+        if let Ok(etag) = HeaderValue::from_str(&self.etag.to_string()) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Ok(cache_control) = HeaderValue::from_str(&self.cache_control) {
+            headers.insert(header::CACHE_CONTROL, cache_control);
+        }
+        if !headers.contains_key(header::VARY) {
+            headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+        }
+
+        response
+    }
+}
@@ -6,6 +6,15 @@ use std::env;
 #[derive(Debug, Clone, Deserialize)]
 pub struct DomainQueryConfig {
     pub per_page: u32,
+    /// bounds for the length of hash prefixes sent by clients
+    pub min_prefix_length: usize,
+    pub max_prefix_length: usize,
+    /// maximal count of prefixes in a single bulk or batch request
+    pub max_batch_prefixes: usize,
+    /// minimal count of domains a hash prefix has to match
+    pub k_anonymity: u32,
+    /// widen prefixes matching fewer than `k_anonymity` domains instead of rejecting them
+    pub widen_prefixes: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -39,6 +48,126 @@ pub struct APIConfig {
     pub port: u32,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PaddingConfig {
+    /// pad fetch responses with decoy domains to this count of entries per requested prefix
+    pub entries: Option<usize>,
+    /// pad fetch responses to this size in bytes per requested prefix
+    pub bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrefixListConfig {
+    /// length of the fqdn hash prefixes published in the prefix list
+    pub prefix_length: usize,
+    /// ascending lower bounds of the score bands, a score below the first bound is in band 0
+    pub bands: Vec<f32>,
+    /// count of published lists kept for delta updates, older clients have to download the full list
+    pub delta_versions: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScoringConfig {
+    /// threads used for propagating scores through the graph
+    pub workers: usize,
+    /// value of trust assigned to every seed domain
+    pub root_value: f32,
+    /// expected seconds between two published runs, responses are cached until the next run is due
+    pub interval: u64,
+    /// seconds between two heartbeats of a running run
+    pub heartbeat: u64,
+    /// seconds without a heartbeat after which a run is considered interrupted
+    pub heartbeat_timeout: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrawlConfig {
+    /// maximal count of links in a single batch of a crawl session
+    pub batch_links: usize,
+    /// maximal count of links staged in a crawl session
+    pub session_links: i64,
+    /// seconds after which uncommitted crawl sessions are discarded
+    pub session_ttl: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrontierConfig {
+    /// seconds a leased domain is hidden from other scrapers before it is queued again
+    pub lease_timeout: i64,
+    /// maximal count of domains leased at once
+    pub max_lease: i64,
+    /// seconds after which crawled domains are queued again
+    pub stale_after: i64,
+    /// seconds after which high-value domains, i.e. seeds, domains linked by seeds and domains
+    /// scoring at least `min_score`, are queued again
+    pub value_stale_after: i64,
+    pub min_score: f32,
+    /// minimal seconds between two refills of the frontier
+    pub refill_interval: i64,
+    /// maximal count of domains queued by a single refill
+    pub refill_size: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestConfig {
+    /// count of valid records of a bulk ingestion written in a single transaction
+    pub batch_records: usize,
+    /// count of lines of an offline import loaded in a single transaction
+    pub import_batch_lines: usize,
+    /// count of updates `/update` queues before rejecting further ones, `0` writes updates
+    /// synchronously instead
+    pub queue_capacity: usize,
+    /// count of background workers writing queued updates in batches of `batch_records`
+    pub queue_workers: usize,
+    /// keep queued updates in postgres, so they survive restarts and are shared between instances
+    pub queue_durable: bool,
+    /// milliseconds an idle worker waits before checking the queue again
+    pub queue_poll_interval: u64,
+    /// seconds a batch of the durable queue is leased to a worker before it is written again
+    pub queue_lease_timeout: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdempotencyConfig {
+    /// seconds the result of a request is replayed for retries with the same `Idempotency-Key`
+    pub retention: i64,
+    /// seconds a request may take before a retry with the same key takes over its claim
+    pub lease: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuarantineConfig {
+    /// seconds rejected payloads are kept for debugging
+    pub retention: i64,
+    /// count of payloads kept per scraper, further rejections are only reported to the scraper
+    pub max_payloads: i64,
+    /// also quarantine payloads of requests without a scraper token, which share a single quota
+    pub anonymous: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LimitsConfig {
+    /// maximal size of JSON bodies and of single lines of bulk ingestions in bytes
+    pub body_bytes: usize,
+    /// maximal count of links of a single update
+    pub links: usize,
+    /// maximal length of submitted fqdns in bytes, fqdns are never longer than 255 bytes
+    pub fqdn_length: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScrapersConfig {
+    /// accept crawl results without a scraper token
+    pub anonymous: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AdminConfig {
+    /// bearer token required for all admin routes, read from `BACKEND_ADMIN_TOKEN`.
+    /// The admin routes are disabled without a token
+    pub token: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub debug: bool,
@@ -46,6 +175,19 @@ pub struct Config {
     pub api: APIConfig,
     pub tracing: TracingConfig,
     pub caching: CachingConfig,
+    #[serde(default)]
+    pub padding: PaddingConfig,
+    pub prefix_list: PrefixListConfig,
+    pub scoring: ScoringConfig,
+    pub crawl: CrawlConfig,
+    pub frontier: FrontierConfig,
+    pub ingest: IngestConfig,
+    pub idempotency: IdempotencyConfig,
+    pub quarantine: QuarantineConfig,
+    pub scrapers: ScrapersConfig,
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
 }
 
 impl Config {
@@ -73,6 +215,14 @@ impl Config {
         // Add in settings from the environment (with a prefix of BACKEND)
         s.merge(Environment::with_prefix("backend"))?;
 
+        // The admin token is a secret, so it isn't part of the checked in files
+        if let Some(token) = env::var("BACKEND_ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())
+        {
+            s.set("admin.token", token)?;
+        }
+
         // You can deserialize (and thus freeze) the entire configuration as
         s.try_into()
     }
@@ -84,4 +234,28 @@ lazy_static! {
         "per_page is limited too {} for this instance",
         CONFIG.database.domains.per_page
     );
+    pub static ref PREFIX_LENGTH_ERROR: String = format!(
+        "Hash prefix has to be between {} and {} characters long",
+        CONFIG.database.domains.min_prefix_length, CONFIG.database.domains.max_prefix_length
+    );
+    pub static ref BATCH_PREFIXES_ERROR: String = format!(
+        "Requests are limited to {} prefixes for this instance",
+        CONFIG.database.domains.max_batch_prefixes
+    );
+    pub static ref BATCH_LINKS_ERROR: String = format!(
+        "Batches are limited to {} links for this instance",
+        CONFIG.crawl.batch_links
+    );
+    pub static ref SESSION_LINKS_ERROR: String = format!(
+        "Crawl sessions are limited to {} links for this instance",
+        CONFIG.crawl.session_links
+    );
+    pub static ref MAX_LEASE_ERROR: String = format!(
+        "Leases have to be between 1 and {} domains for this instance",
+        CONFIG.frontier.max_lease
+    );
+    pub static ref K_ANONYMITY_ERROR: String = format!(
+        "Hash prefix has to match at least {} domains, please send a shorter prefix",
+        CONFIG.database.domains.k_anonymity
+    );
 }
@@ -1,7 +1,8 @@
 // imports
+use super::config::CONFIG;
 use actix_web::{
-    error::BlockingError, error::ResponseError, http::StatusCode, Error as WebError, HttpResponse,
-    HttpResponseBuilder,
+    error::BlockingError, error::JsonPayloadError, error::QueryPayloadError, error::ResponseError,
+    http::StatusCode, Error as WebError, HttpRequest, HttpResponse, HttpResponseBuilder,
 };
 use serde::Serialize;
 use sqlx::error::Error as SQLError;
@@ -35,6 +36,12 @@ pub enum APIError {
     EmptyError,
     /// Integrity Error raised by Database
     IntegrityError,
+    /// Used when a request conflicts with the current state of a resource, e.g. a job that is already running
+    ConflictError(String),
+    /// Used when the service is temporarily unable to accept a request, e.g. a full queue. Clients should retry later
+    UnavailableError(String),
+    /// Used by endpoints that are documented but not implemented yet
+    NotImplementedError(String),
 }
 
 #[derive(Serialize)]
@@ -52,7 +59,7 @@ pub struct APIErrorWrapper {
 
 impl fmt::Display for APIError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.to_string().as_str())
+        f.write_str(&String::from(self))
     }
 }
 
@@ -91,12 +98,27 @@ impl ResponseError for APIError {
             APIError::PoolError => StatusCode::TOO_MANY_REQUESTS,
             APIError::AuthError { .. } => StatusCode::UNAUTHORIZED,
             APIError::NotFoundError { .. } => StatusCode::NOT_FOUND,
+            APIError::ConflictError { .. } => StatusCode::CONFLICT,
+            APIError::UnavailableError { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            APIError::NotImplementedError { .. } => StatusCode::NOT_IMPLEMENTED,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
 impl APIError {
+    /// Validation error of a body that couldn't be deserialized. The position serde appends is
+    /// dropped, it's meaningless for single lines of bulk ingestions
+    pub fn from_json(error: &serde_json::Error) -> Self {
+        let msg = error.to_string();
+        let position = format!(" at line {} column {}", error.line(), error.column());
+
+        Self::ValidationError(
+            ["body"],
+            msg.strip_suffix(&position).unwrap_or(&msg).to_owned(),
+        )
+    }
+
     pub fn to_wrapped(&self) -> APIErrorWrapper {
         APIErrorWrapper {
             detail: APIErrorDetails {
@@ -119,6 +141,9 @@ impl APIError {
             APIError::AuthError(_) => "Security Error",
             APIError::NotFoundError => "Not Found",
             APIError::EmptyError | APIError::IntegrityError => "Internal Error",
+            APIError::ConflictError(_) => "Conflict",
+            APIError::UnavailableError(_) => "Service Unavailable",
+            APIError::NotImplementedError(_) => "Not Implemented",
         }
     }
 }
@@ -130,6 +155,27 @@ impl From<WebError> for APIError {
     }
 }
 
+// Report malformed query strings as APIErrors instead of actix' plain text errors
+pub fn query_error_handler(error: QueryPayloadError, _req: &HttpRequest) -> WebError {
+    APIError::ValidationError(["query"], error.to_string()).into()
+}
+
+// Report malformed or oversized JSON bodies as APIErrors instead of actix' plain text errors
+pub fn json_error_handler(error: JsonPayloadError, _req: &HttpRequest) -> WebError {
+    match error {
+        JsonPayloadError::Deserialize(error) => APIError::from_json(&error),
+        error if error.status_code() == StatusCode::PAYLOAD_TOO_LARGE => APIError::ValidationError(
+            ["body"],
+            format!(
+                "The body exceeds the limit of {} bytes",
+                CONFIG.limits.body_bytes
+            ),
+        ),
+        error => APIError::ValidationError(["body"], error.to_string()),
+    }
+    .into()
+}
+
 // Convert Thread Blocking Errors to APIErrors
 impl From<BlockingError> for APIError {
     fn from(error: BlockingError) -> Self {
@@ -148,6 +194,9 @@ impl From<APIError> for String {
             APIError::PoolError => "Exhausted Pool connections".to_owned(),
             APIError::EmptyError => "Empty Error".to_owned(),
             APIError::IntegrityError => "Internal Error".to_owned(),
+            APIError::ConflictError(message) => message.to_owned(),
+            APIError::UnavailableError(message) => message.to_owned(),
+            APIError::NotImplementedError(message) => message.to_owned(),
         }
     }
 }
@@ -163,6 +212,9 @@ impl From<&APIError> for String {
             APIError::AuthError(message) => message.clone().to_owned(),
             APIError::PoolError => "Exhausted Pool connections".to_owned(),
             APIError::IntegrityError => "Internal Error".to_owned(),
+            APIError::ConflictError(message) => message.clone().to_owned(),
+            APIError::UnavailableError(message) => message.clone().to_owned(),
+            APIError::NotImplementedError(message) => message.clone().to_owned(),
         }
     }
 }
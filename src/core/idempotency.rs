@@ -0,0 +1,109 @@
+use super::config::CONFIG;
+use super::errors::APIError;
+use super::types::APIResponse;
+use crate::db::models::IdempotencyKey;
+use crate::db::util::DbPool;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponseBuilder, ResponseError};
+use serde::Serialize;
+use std::future::Future;
+
+/// Header clients send to make retries of a request safe
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+/// Header marking a response as replayed from an earlier request with the same key
+pub const IDEMPOTENT_REPLAYED: &str = "Idempotent-Replayed";
+
+/// Idempotency key sent with the request, if any
+pub fn key(req: &HttpRequest) -> Result<Option<String>, APIError> {
+    match req.headers().get(IDEMPOTENCY_KEY).map(|key| key.to_str()) {
+        None => Ok(None),
+        Some(Ok(key)) if !key.is_empty() && key.len() <= 255 => Ok(Some(key.to_owned())),
+        Some(_) => Err(APIError::ValidationError(
+            [IDEMPOTENCY_KEY],
+            "Idempotency-Key has to be 1 to 255 visible ASCII characters".to_owned(),
+        )),
+    }
+}
+
+/// Fingerprint of a request body, keys may only be reused for the same body
+pub fn fingerprint<T: Serialize>(body: &T) -> Result<String, APIError> {
+    let body = serde_json::to_vec(body).map_err(|e| APIError::InternalError(e.to_string()))?;
+
+    Ok(blake3::hash(&body).to_hex().to_string())
+}
+
+/// Execute `request` at most once per idempotency key of a scraper, keys of different scrapers
+/// don't collide.
+///
+/// Retries with the same key get the result of the first request replayed as long as it is
+/// retained, see `idempotency.retention`. Reusing a key for a different request or while the
+/// first request is still processed is a conflict. A request that didn't finish within
+/// `idempotency.lease`, e.g. because the instance crashed, is retried instead. Server errors aren't
+/// stored, so the request can be retried. Bodies of successful responses aren't stored either,
+/// which suits submissions answered with an empty `202 Accepted`.
+pub async fn once<F>(
+    pool: &DbPool,
+    key: &str,
+    scraper_id: Option<i32>,
+    fingerprint: &str,
+    request: F,
+) -> APIResponse
+where
+    F: Future<Output = APIResponse>,
+{
+    let config = &CONFIG.idempotency;
+    if let Some(stored) = IdempotencyKey::claim(
+        pool,
+        key,
+        scraper_id,
+        fingerprint,
+        config.retention,
+        config.lease,
+    )
+    .await?
+    {
+        return replay(stored, fingerprint);
+    }
+
+    let result = request.await;
+    match &result {
+        Ok(response) => {
+            let status = response.status().as_u16() as i16;
+            IdempotencyKey::complete(pool, key, scraper_id, status, None).await?
+        }
+        Err(e) if e.status_code().is_client_error() => {
+            let body = serde_json::to_string(&e.to_wrapped()).ok();
+            let status = e.status_code().as_u16() as i16;
+            IdempotencyKey::complete(pool, key, scraper_id, status, body).await?
+        }
+        Err(_) => IdempotencyKey::release(pool, key, scraper_id).await?,
+    }
+
+    result
+}
+
+fn replay(stored: IdempotencyKey, fingerprint: &str) -> APIResponse {
+    if stored.fingerprint != fingerprint {
+        return Err(APIError::ConflictError(
+            "Idempotency-Key was already used for a different request".to_owned(),
+        ));
+    }
+
+    let status = match stored.status {
+        Some(status) => StatusCode::from_u16(status as u16)
+            .map_err(|e| APIError::InternalError(e.to_string()))?,
+        None => {
+            return Err(APIError::ConflictError(
+                "A request with this Idempotency-Key is still being processed".to_owned(),
+            ))
+        }
+    };
+
+    let mut builder = HttpResponseBuilder::new(status);
+    builder.insert_header((IDEMPOTENT_REPLAYED, "true"));
+
+    Ok(match stored.body {
+        Some(body) => builder.content_type("application/json").body(body),
+        None => builder.finish(),
+    })
+}
@@ -0,0 +1,43 @@
+//! Checks of the fields with a configurable limit. They are applied when a request is validated,
+//! so an update exceeding a limit is quarantined like any other invalid update. The error names
+//! the offending field.
+
+use super::config::CONFIG;
+use super::errors::APIError;
+
+/// Reject `value` of `field` if it's longer than `limit` bytes
+pub fn check_length(field: &'static str, value: &str, limit: usize) -> Result<(), APIError> {
+    match value.len() > limit {
+        true => Err(APIError::ValidationError(
+            [field],
+            format!("{} exceeds the limit of {} bytes", field, limit),
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Reject a fqdn of `field` longer than `limits.fqdn_length` bytes
+pub fn check_fqdn(field: &'static str, fqdn: &str) -> Result<(), APIError> {
+    check_length(field, fqdn, CONFIG.limits.fqdn_length)
+}
+
+/// Reject more than `limits.links` links of a single update or links longer than `limits.fqdn_length` bytes
+pub fn check_links(links: &[String]) -> Result<(), APIError> {
+    let limit = CONFIG.limits.links;
+
+    if links.len() > limit {
+        return Err(APIError::ValidationError(
+            ["links"],
+            format!(
+                "{} links exceed the limit of {} links per update, split them with a crawl session",
+                links.len(),
+                limit
+            ),
+        ));
+    }
+    for link in links {
+        check_fqdn("links", link)?;
+    }
+
+    Ok(())
+}
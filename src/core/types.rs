@@ -1,5 +1,104 @@
 use super::errors::APIError;
-use actix_web::HttpResponse;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder, ResponseError};
+use serde::Serialize;
 
 // types
 pub type APIResponse = Result<HttpResponse, APIError>;
+
+/// Response formats clients may request with the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl Format {
+    /// Pick the supported format with the highest quality in the `Accept` header, JSON if none is supported
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let accept = match req
+            .headers()
+            .get(header::ACCEPT)
+            .map(|accept| accept.to_str())
+        {
+            Some(Ok(accept)) => accept,
+            _ => return Self::Json,
+        };
+
+        let mut negotiated = (Self::Json, 0.0);
+        for range in accept.split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let format = match params.next() {
+                Some("application/json") | Some("application/*") | Some("*/*") => Self::Json,
+                Some("application/cbor") => Self::Cbor,
+                Some("application/msgpack") | Some("application/x-msgpack") => Self::MessagePack,
+                _ => continue,
+            };
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if quality > negotiated.1 {
+                negotiated = (format, quality);
+            }
+        }
+
+        negotiated.0
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Cbor => "application/cbor",
+            Self::MessagePack => "application/msgpack",
+        }
+    }
+
+    pub fn serialize<T: Serialize>(&self, body: &T) -> Result<Vec<u8>, APIError> {
+        match self {
+            Self::Json => {
+                serde_json::to_vec(body).map_err(|e| APIError::InternalError(e.to_string()))
+            }
+            Self::Cbor => {
+                serde_cbor::to_vec(body).map_err(|e| APIError::InternalError(e.to_string()))
+            }
+            // structs are encoded as maps to keep the field names, like in JSON and CBOR
+            Self::MessagePack => {
+                rmp_serde::to_vec_named(body).map_err(|e| APIError::InternalError(e.to_string()))
+            }
+        }
+    }
+}
+
+/// Responder serializing the body in the format negotiated with the client, see [`Format`]
+pub struct Negotiated<T> {
+    builder: HttpResponseBuilder,
+    body: T,
+}
+
+impl<T: Serialize> Negotiated<T> {
+    pub fn new(builder: HttpResponseBuilder, body: T) -> Self {
+        Self { builder, body }
+    }
+
+    pub fn ok(body: T) -> Self {
+        Self::new(HttpResponse::Ok(), body)
+    }
+}
+
+impl<T: Serialize> Responder for Negotiated<T> {
+    fn respond_to(mut self, req: &HttpRequest) -> HttpResponse {
+        let format = Format::negotiate(req);
+
+        match format.serialize(&self.body) {
+            Ok(body) => self
+                .builder
+                .insert_header((header::CONTENT_TYPE, format.content_type()))
+                .append_header((header::VARY, "Accept"))
+                .body(body),
+            Err(e) => e.error_response(),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod frontier;
+pub mod requests;
+pub mod responses;
+pub mod routes;
@@ -0,0 +1,52 @@
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::core::util::timestamp;
+use crate::db::models::{CrawlFrontier, FrontierItem, ScoreRun};
+use crate::db::util::DbPool;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Server-managed crawl frontier.
+///
+/// Domains that were never crawled, are stale by `last_updated` or are high-value, i.e. near
+/// seeds or high scoring, are queued in `crawl_frontier`. Scrapers lease domains for
+/// `frontier.lease_timeout` seconds and acknowledge them by submitting an update for the domain.
+/// Leases that are never acknowledged expire and the domain can be leased again.
+/// The queue is refilled on demand once scrapers drain it.
+#[derive(Default)]
+pub struct Frontier {
+    /// unix timestamp of the last refill
+    last_refill: AtomicI64,
+}
+
+impl Frontier {
+    /// Lease up to `count` domains, refilling the queue if it can't satisfy the lease
+    pub async fn lease(&self, pool: &DbPool, count: i64) -> Result<Vec<FrontierItem>, APIError> {
+        let config = &CONFIG.frontier;
+        let mut items = CrawlFrontier::lease(pool, count, config.lease_timeout).await?;
+
+        if (items.len() as i64) < count && self.claim_refill() {
+            let run = ScoreRun::latest(pool).await?;
+            let queued = CrawlFrontier::refill(pool, run.map(|run| run.id), config).await?;
+            tracing::info!("Queued {} domains in the crawl frontier", queued);
+
+            items.extend(
+                CrawlFrontier::lease(pool, count - items.len() as i64, config.lease_timeout)
+                    .await?,
+            );
+        }
+
+        Ok(items)
+    }
+
+    /// Claim the next refill, refills run at most once per `frontier.refill_interval` seconds
+    fn claim_refill(&self) -> bool {
+        let now = timestamp();
+        let last_refill = self.last_refill.load(Ordering::Relaxed);
+
+        now - last_refill >= CONFIG.frontier.refill_interval
+            && self
+                .last_refill
+                .compare_exchange(last_refill, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+    }
+}
@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+/// # SessionRequest
+/// Opens a crawl session for a source domain. Scrapers stream the links of large domains in
+/// batches into the session and commit it once the crawl is finished, replacing all links of the
+/// source at once.
+pub struct SessionRequest {
+    pub fqdn: String,
+    pub network: bool,
+    pub last_updated: i64,
+}
+
+#[derive(Deserialize, Debug)]
+/// # LinkBatchRequest
+/// Batch of links found on the source of a crawl session.
+/// Batches are limited to `crawl.batch_links` links, sessions to `crawl.session_links` links.
+pub struct LinkBatchRequest {
+    pub links: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+/// # LeaseRequest
+/// Lease domains to crawl from the frontier. Leases expire after `frontier.lease_timeout` seconds,
+/// scrapers acknowledge a domain by submitting an update for it.
+pub struct LeaseRequest {
+    #[serde(default = "default_count")]
+    pub count: i64,
+}
+
+fn default_count() -> i64 {
+    1
+}
@@ -0,0 +1,62 @@
+use crate::db::models::{CrawlSession, FrontierItem};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: i32,
+    pub fqdn: String,
+    pub network: bool,
+    pub last_updated: i64,
+    /// unix timestamp after which the session expires if it wasn't committed
+    pub expires_at: i64,
+}
+
+impl SessionResponse {
+    pub fn new(session: CrawlSession, ttl: i64) -> Self {
+        Self {
+            id: session.id,
+            fqdn: session.fqdn,
+            network: session.network,
+            last_updated: session.last_updated,
+            expires_at: session.created_at + ttl,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LinkBatchResponse {
+    /// count of distinct links staged in the session so far
+    pub staged: i64,
+}
+
+#[derive(Serialize)]
+pub struct CommitResponse {
+    /// count of links the source has after the commit
+    pub links: u64,
+}
+
+#[derive(Serialize)]
+pub struct LeaseResponse {
+    pub leases: Vec<Lease>,
+}
+
+#[derive(Serialize)]
+pub struct Lease {
+    pub fqdn: String,
+    pub network: bool,
+    /// why the domain was queued: `value`, `unseen` or `stale`
+    pub reason: String,
+    /// unix timestamp after which the domain is handed out again if it wasn't updated
+    pub leased_until: i64,
+}
+
+impl From<FrontierItem> for Lease {
+    fn from(item: FrontierItem) -> Self {
+        Self {
+            fqdn: item.fqdn,
+            network: item.network,
+            reason: item.reason,
+            leased_until: item.leased_until,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use super::frontier::Frontier;
+use super::requests::{LeaseRequest, LinkBatchRequest, SessionRequest};
+use super::responses::{CommitResponse, LeaseResponse, LinkBatchResponse, SessionResponse};
+use crate::core::auth::Scraper;
+use crate::core::config::{BATCH_LINKS_ERROR, CONFIG, MAX_LEASE_ERROR, SESSION_LINKS_ERROR};
+use crate::core::errors::APIError;
+use crate::core::limits;
+use crate::core::types::APIResponse;
+use crate::db::models::CrawlSession;
+use crate::db::util::DbPool;
+use crate::domains::util::is_fqdn;
+use actix_web::{
+    delete, post,
+    web::{Data, Json, Path, ServiceConfig},
+    HttpResponse,
+};
+
+#[post("/sessions")]
+async fn create_session(
+    scraper: Scraper,
+    data: Json<SessionRequest>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    limits::check_fqdn("fqdn", &data.fqdn)?;
+    if !is_fqdn(&data.fqdn) {
+        return Err(APIError::ValidationError(
+            ["fqdn"],
+            "The supplied source is not a valid fqdn".to_owned(),
+        ));
+    }
+
+    let ttl = CONFIG.crawl.session_ttl;
+    let session = CrawlSession::create(
+        pool.as_ref(),
+        &data.fqdn,
+        data.network,
+        data.last_updated,
+        ttl,
+        scraper.id(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(SessionResponse::new(session, ttl)))
+}
+
+/// get an open session, only the scraper that opened it may use it
+async fn owned_session(
+    pool: &DbPool,
+    id: i32,
+    scraper: &Scraper,
+) -> Result<CrawlSession, APIError> {
+    let session = CrawlSession::get(pool, id, CONFIG.crawl.session_ttl).await?;
+
+    if session.scraper_id != scraper.id() {
+        return Err(APIError::AuthError(
+            "The session was opened by another scraper".to_owned(),
+        ));
+    }
+
+    Ok(session)
+}
+
+#[post("/sessions/{id}/links")]
+async fn add_links(
+    scraper: Scraper,
+    id: Path<i32>,
+    data: Json<LinkBatchRequest>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    if data.links.len() > CONFIG.crawl.batch_links {
+        return Err(APIError::ValidationError(
+            ["links"],
+            BATCH_LINKS_ERROR.to_owned(),
+        ));
+    }
+    for link in &data.links {
+        limits::check_fqdn("links", link)?;
+    }
+    if let Some(link) = data.links.iter().find(|link| !is_fqdn(link)) {
+        return Err(APIError::ValidationError(
+            ["links"],
+            format!("The supplied link {} is not a valid fqdn", link),
+        ));
+    }
+
+    let pool_ref = pool.as_ref();
+    let session = owned_session(pool_ref, id.into_inner(), &scraper).await?;
+
+    // duplicates may be counted twice here, the limit is a safeguard and not exact
+    if session.link_count(pool_ref).await? + data.links.len() as i64 > CONFIG.crawl.session_links {
+        return Err(APIError::ValidationError(
+            ["links"],
+            SESSION_LINKS_ERROR.to_owned(),
+        ));
+    }
+
+    session.add_links(pool_ref, &data.links).await?;
+
+    Ok(HttpResponse::Ok().json(LinkBatchResponse {
+        staged: session.link_count(pool_ref).await?,
+    }))
+}
+
+#[post("/sessions/{id}/commit")]
+async fn commit_session(scraper: Scraper, id: Path<i32>, pool: Data<DbPool>) -> APIResponse {
+    let session = owned_session(pool.as_ref(), id.into_inner(), &scraper).await?;
+    let links = session.commit(pool.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(CommitResponse { links }))
+}
+
+#[delete("/sessions/{id}")]
+async fn abort_session(scraper: Scraper, id: Path<i32>, pool: Data<DbPool>) -> APIResponse {
+    let session = owned_session(pool.as_ref(), id.into_inner(), &scraper).await?;
+    CrawlSession::abort(pool.as_ref(), session.id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/frontier/leases")]
+async fn lease(
+    scraper: Scraper,
+    data: Json<LeaseRequest>,
+    frontier: Data<Frontier>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    // leased domains are withheld from other scrapers, so anonymous scrapers can't lease them
+    if scraper.id().is_none() {
+        return Err(APIError::AuthError(
+            "Leasing domains requires a scraper token".to_owned(),
+        ));
+    }
+    if data.count < 1 || data.count > CONFIG.frontier.max_lease {
+        return Err(APIError::ValidationError(
+            ["count"],
+            MAX_LEASE_ERROR.to_owned(),
+        ));
+    }
+
+    let leases = frontier.lease(pool.as_ref(), data.count).await?;
+
+    Ok(HttpResponse::Ok().json(LeaseResponse {
+        leases: leases.into_iter().map(Into::into).collect(),
+    }))
+}
+
+pub fn services(cfg: &mut ServiceConfig) {
+    cfg.service(create_session);
+    cfg.service(add_links);
+    cfg.service(commit_session);
+    cfg.service(abort_session);
+    cfg.service(lease);
+}
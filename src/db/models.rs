@@ -1,10 +1,27 @@
 use super::util::trim_zero as trim;
-use crate::{core::errors::APIError, domains::responses::DomainResponse};
+use crate::{
+    core::{
+        config::{FrontierConfig, QuarantineConfig},
+        errors::APIError,
+        util::timestamp,
+    },
+    domains::requests::UpdateRequest,
+    domains::responses::{DomainResponse, PrefixEntry},
+    ruegen::responses::{RuegeSummary, RuegenInformation},
+};
 use sqlx::postgres::PgQueryResult;
+use sqlx::{Postgres, Transaction};
+use std::collections::HashMap;
 
-use super::util::DbPool;
+use super::util::{Cursor, DbPool};
 
-pub struct Client;
+/// Registered API client, e.g. a scraper, authenticated by its token
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub id: i32,
+    pub name: String,
+    pub created_at: i64,
+}
 
 pub struct Role;
 
@@ -41,15 +58,355 @@ pub struct DomainLink {
     pub target_id: i32,
 }
 
+/// Provenance of a link, every scraper reporting a link has its own report
+pub struct LinkReport {
+    pub source_id: i32,
+    pub target_id: i32,
+    /// client of the reporting scraper, `None` for anonymous and imported links
+    pub scraper_id: Option<i32>,
+    /// time the server received the first and the last report of the link
+    pub first_reported_at: i64,
+    pub last_reported_at: i64,
+}
+
+pub struct DomainAlias {
+    pub alias_id: i32,
+    pub canonical_id: i32,
+}
+
+#[derive(Debug)]
+pub struct Seed {
+    pub domain_id: i32,
+    pub network: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreRunState {
+    Running,
+    Finished,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScoreRun {
+    pub id: i32,
+    pub state: String,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+/// Crawl session of a scraper, replacing the links of `fqdn` on commit
+#[derive(Debug, Clone)]
+pub struct CrawlSession {
+    pub id: i32,
+    pub fqdn: String,
+    pub network: bool,
+    pub last_updated: i64,
+    pub created_at: i64,
+    pub scraper_id: Option<i32>,
+}
+
+/// Queue of domains scrapers lease work from, see `crawl::frontier`
+pub struct CrawlFrontier;
+
+/// Domain leased from the crawl frontier
+#[derive(Debug)]
+pub struct FrontierItem {
+    pub fqdn: String,
+    pub network: bool,
+    /// why the domain was queued: `value`, `unseen` or `stale`
+    pub reason: String,
+    pub leased_until: i64,
+}
+
+/// Result of a request sent with an `Idempotency-Key`, `status` is `None` while the request is processed
+#[derive(Debug)]
+pub struct IdempotencyKey {
+    pub key: String,
+    /// hash of the request body, a key may only be reused for the same request
+    pub fingerprint: String,
+    pub status: Option<i16>,
+    pub body: Option<String>,
+    pub created_at: i64,
+}
+
+/// Offline import of a file, see `commands::import`
+pub struct Import;
+
+/// Payload rejected by an ingestion endpoint, see `domains::quarantine`
+#[derive(Debug)]
+pub struct QuarantinedPayload {
+    pub id: i32,
+    /// the payload as received, it may not even be JSON
+    pub payload: String,
+    pub reason: String,
+    /// field the rejection pointed at
+    pub loc: Option<String>,
+    pub scraper_id: Option<i32>,
+    /// endpoint or worker that rejected the payload, e.g. `update`
+    pub source: String,
+    pub quarantined_at: i64,
+}
+
+/// Update waiting in the durable ingestion queue, see `domains::queue`
+#[derive(Debug)]
+pub struct IngestQueueEntry {
+    pub id: i64,
+    /// the queued `UpdateRequest` as JSON
+    pub payload: String,
+    pub scraper_id: Option<i32>,
+    pub enqueued_at: i64,
+}
+
+/// Scores of a domain in a run, `score[0]` is the score in the network `true` and `score[1]` in the network `false`
+#[derive(Debug)]
+pub struct DomainScore {
+    pub domain_id: i32,
+    pub score: [f32; 2],
+}
+
+/// Prefix list published for a finished scoring run, identified by the id of the run
+#[derive(Debug, Clone)]
+pub struct PrefixList {
+    pub run_id: i32,
+    pub prefix_length: i32,
+    pub bands: Vec<f32>,
+    pub published_at: i64,
+}
+
+/// Entry that differs between two published prefix lists. `bands` is `None` if the prefix was removed
+#[derive(Debug)]
+pub struct PrefixChange {
+    pub prefix: String,
+    pub bands: Option<[u8; 2]>,
+    pub added: bool,
+}
+
+/// Highest scores of all domains sharing a fqdn hash prefix in a run
+#[derive(Debug)]
+pub struct PrefixScore {
+    pub prefix: String,
+    pub score: [f32; 2],
+}
+
+impl Client {
+    #[tracing::instrument(skip(token_hash))]
+    /// Register a client with the hash of its token and grant it `role`
+    pub async fn create(
+        pool: &DbPool,
+        name: &str,
+        token_hash: &str,
+        role: &str,
+    ) -> Result<Client, APIError> {
+        let mut tx = pool.begin().await?;
+
+        let client = sqlx::query_as!(
+            Client,
+            r#"insert into clients (name, token_hash, created_at)
+               values ($1, $2, $3)
+               returning id, name, created_at"#,
+            name,
+            token_hash,
+            timestamp()
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"insert into client_roles (client_id, role_id)
+               select $1, id from roles where name = $2"#,
+            client.id,
+            role
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(client)
+    }
+
+    #[tracing::instrument(skip(token_hash))]
+    /// get the client with the token hashed to `token_hash`, if it was granted `role`
+    pub async fn by_token(
+        pool: &DbPool,
+        token_hash: &str,
+        role: &str,
+    ) -> Result<Option<Client>, APIError> {
+        Ok(sqlx::query_as!(
+            Client,
+            r#"select c.id, c.name, c.created_at
+               from clients c
+               join client_roles cr on cr.client_id = c.id
+               join roles r on r.id = cr.role_id
+               where c.token_hash = $1 and r.name = $2"#,
+            token_hash,
+            role
+        )
+        .fetch_optional(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// get all clients granted `role`
+    pub async fn list(pool: &DbPool, role: &str) -> Result<Vec<Client>, APIError> {
+        Ok(sqlx::query_as!(
+            Client,
+            r#"select c.id, c.name, c.created_at
+               from clients c
+               join client_roles cr on cr.client_id = c.id
+               join roles r on r.id = cr.role_id
+               where r.name = $1
+               order by c.id"#,
+            role
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+}
+
+impl Ruege {
+    #[tracing::instrument]
+    /// get all rügen of the domains matching a fqdn hash prefix.
+    /// Starts after `cursor` if given, otherwise at `page`. Returns the cursor of the next page if there is one
+    pub async fn by_hash(
+        pool: &DbPool,
+        hash: &String,
+        per_page: u32,
+        page: u32,
+        cursor: Option<&Cursor>,
+    ) -> Result<(Vec<RuegenInformation>, Option<Cursor>), APIError> {
+        let mut ruegen = sqlx::query!(
+            r#"select r.id, d.fqdn_hash, d.fqdn, r.identifier, r.title, r.ziffer, r.year
+        from ruegen r
+        join ruegen_domains rd on rd.ruegen_id = r.id
+        join domains d on d.id = rd.domain_id
+        where d.fqdn_hash like concat($1::text, '%')
+            and ($2::text is null or (d.fqdn_hash, r.id) > ($2::text, $3::int))
+        order by d.fqdn_hash, r.id
+        limit $4
+        offset $5"#,
+            trim(hash),
+            cursor.map(|cursor| cursor.fqdn_hash.as_str()),
+            cursor.map_or(0, |cursor| cursor.id),
+            per_page as i64 + 1,
+            offset(per_page, page, cursor)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let next = next_cursor(&mut ruegen, per_page, |rec| Cursor {
+            fqdn_hash: rec.fqdn_hash.clone(),
+            id: rec.id,
+        });
+
+        Ok((
+            ruegen
+                .into_iter()
+                .map(|rec| RuegenInformation {
+                    medium: rec.fqdn,
+                    identified: rec.identifier,
+                    title: rec.title,
+                    ziffer: rec.ziffer,
+                    year: rec.year as u16,
+                })
+                .collect(),
+            next,
+        ))
+    }
+
+    #[tracing::instrument(skip(hashes))]
+    /// get all rügen of the domains matching any of multiple fqdn hash prefixes with a single query.
+    /// Pagination is applied to each prefix
+    pub async fn by_hashes(
+        pool: &DbPool,
+        hashes: &[String],
+        per_page: u32,
+        page: u32,
+    ) -> Result<Vec<RuegenInformation>, APIError> {
+        let hashes: Vec<String> = hashes.iter().map(|hash| trim(hash)).collect();
+
+        Ok(sqlx::query!(
+            r#"select r.fqdn as "fqdn!", r.identifier as "identifier!", r.title as "title!",
+            r.ziffer as "ziffer!", r.year as "year!"
+        from unnest($1::text[]) p(prefix)
+        cross join lateral (
+            select d.fqdn_hash, d.fqdn, r.id, r.identifier, r.title, r.ziffer, r.year
+            from ruegen r
+            join ruegen_domains rd on rd.ruegen_id = r.id
+            join domains d on d.id = rd.domain_id
+            where d.fqdn_hash like concat(p.prefix, '%')
+            order by d.fqdn_hash, r.id
+            limit $2
+            offset $3
+        ) r
+        order by p.prefix, r.fqdn_hash, r.id"#,
+            &hashes,
+            per_page as i64,
+            per_page as i64 * page as i64
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| RuegenInformation {
+            medium: rec.fqdn,
+            identified: rec.identifier,
+            title: rec.title,
+            ziffer: rec.ziffer,
+            year: rec.year as u16,
+        })
+        .collect())
+    }
+}
+
+impl Ruege {
+    #[tracing::instrument(skip(fqdns))]
+    /// get the rügen linked to multiple domains, grouped by fqdn.
+    /// Aliases get the rügen of their canonical domain as well
+    pub async fn by_fqdns(
+        pool: &DbPool,
+        fqdns: &[String],
+    ) -> Result<HashMap<String, Vec<RuegeSummary>>, APIError> {
+        let mut ruegen: HashMap<String, Vec<RuegeSummary>> = HashMap::new();
+
+        for rec in sqlx::query!(
+            r#"select distinct d.fqdn, r.id, r.identifier, r.title, r.ziffer, r.year
+        from domains d
+        left join domain_aliases a on a.alias_id = d.id
+        join ruegen_domains rd on rd.domain_id = d.id or rd.domain_id = a.canonical_id
+        join ruegen r on r.id = rd.ruegen_id
+        where d.fqdn = any($1)
+        order by d.fqdn, r.year desc, r.id"#,
+            fqdns
+        )
+        .fetch_all(pool)
+        .await?
+        {
+            ruegen.entry(rec.fqdn).or_default().push(RuegeSummary {
+                identifier: rec.identifier,
+                title: rec.title,
+                ziffer: rec.ziffer,
+                year: rec.year as u16,
+            });
+        }
+
+        Ok(ruegen)
+    }
+}
+
 impl DomainLink {
     #[tracing::instrument]
+    /// Insert a link and record the report of the scraper, received by the server at `reported_at`
     pub async fn upsert(
         pool: &DbPool,
         source_id: i32,
         target_id: i32,
-    ) -> Result<PgQueryResult, APIError> {
-        // TODO: Make this upsert gracefull and not such a mess
-        Ok(sqlx::query!(
+        scraper_id: Option<i32>,
+        reported_at: i64,
+    ) -> Result<(), APIError> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!(
             r#"insert into domain_link (source_id, target_id)
                values ($1, $2)
                on conflict on constraint dl_pk
@@ -57,134 +414,1856 @@ impl DomainLink {
             source_id,
             target_id
         )
-        .execute(pool)
-        .await?)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"insert into link_reports (source_id, target_id, scraper_id, first_reported_at, last_reported_at)
+               values ($1, $2, $3, $4, $4)
+               on conflict (source_id, target_id, coalesce(scraper_id, 0))
+               do update set last_reported_at = greatest(link_reports.last_reported_at, excluded.last_reported_at)"#,
+            source_id,
+            target_id,
+            scraper_id,
+            reported_at
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
     }
-}
 
-impl Domain {
     #[tracing::instrument]
-    /// get domain id or create new domain
-    /// Is written with select and fallback to insert instead of an upserty-thing
-    pub async fn get_or_create(
+    /// Withdraw the reports of a scraper received within `from..=to`, e.g. after it was found misbehaving. Reports
+    /// are withdrawn if the span between their first and last report overlaps the range. Links no other scraper
+    /// reported are deleted, links reported by others are kept. With `quarantine` the withdrawn reports are moved
+    /// to `quarantined_links` for later inspection. Returns the count of deleted links.
+    pub async fn rollback(
         pool: &DbPool,
-        fqdn: &String,
-        last_updated: i64,
-    ) -> Result<i32, APIError> {
-        match sqlx::query!(r#"select id from domains where fqdn = $1"#, fqdn)
-            .fetch_optional(pool)
-            .await?
-        {
-            Some(rec) => Ok(rec.id),
-            None => Ok(sqlx::query!(
-                r#"
-            select last_updated, id from domains where fqdn = $1
-            "#,
-                fqdn
+        scraper_id: i32,
+        from: i64,
+        to: i64,
+        quarantine: bool,
+    ) -> Result<u64, APIError> {
+        let mut tx = pool.begin().await?;
+
+        if quarantine {
+            sqlx::query!(
+                r#"insert into quarantined_links (source_id, target_id, scraper_id, reported_at, quarantined_at)
+                   select source_id, target_id, scraper_id, last_reported_at, $4
+                   from link_reports
+                   where scraper_id = $1 and last_reported_at >= $2 and first_reported_at <= $3"#,
+                scraper_id,
+                from,
+                to,
+                timestamp()
             )
-            .fetch_one(pool)
-            .await?
-            .id),
+            .execute(&mut tx)
+            .await?;
         }
+
+        // the statement sees link_reports without the withdrawn reports deleted by the cte
+        let deleted = sqlx::query!(
+            r#"with withdrawn as (
+                   delete from link_reports
+                   where scraper_id = $1 and last_reported_at >= $2 and first_reported_at <= $3
+                   returning source_id, target_id
+               )
+               delete from domain_link l
+               using withdrawn w
+               where l.source_id = w.source_id and l.target_id = w.target_id
+                     and not exists (
+                         select 1 from link_reports r
+                         where r.source_id = l.source_id and r.target_id = l.target_id
+                               and r.scraper_id is distinct from $1
+                     )"#,
+            scraper_id,
+            from,
+            to
+        )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+        Ok(deleted)
     }
 }
 
-impl SimpleDomain {
+impl DomainLink {
     #[tracing::instrument]
-    pub async fn get_by_fqdn(pool: &DbPool, fqdn: &str) -> Result<SimpleDomain, APIError> {
-        match sqlx::query!(
-            r#"
-        select last_updated, id from domains where fqdn = $1
-        "#,
-            fqdn
+    /// get all links as `(source_id, target_id, count)` with aliases merged into their canonical domain.
+    /// Links between a domain and its aliases are dropped, the count of links that end up on the same edge is summed up.
+    pub async fn canonical_edges(pool: &DbPool) -> Result<Vec<(i32, i32, u32)>, APIError> {
+        Ok(sqlx::query!(
+            r#"select coalesce(sa.canonical_id, l.source_id) as "source_id!",
+                      coalesce(ta.canonical_id, l.target_id) as "target_id!",
+                      count(*) as "count!"
+               from domain_link l
+               left join domain_aliases sa on sa.alias_id = l.source_id
+               left join domain_aliases ta on ta.alias_id = l.target_id
+               where coalesce(sa.canonical_id, l.source_id) <> coalesce(ta.canonical_id, l.target_id)
+               group by 1, 2"#
         )
-        .fetch_one(pool)
-        .await
-        {
-            Ok(rec) => Ok(SimpleDomain {
-                id: rec.id,
-                last_updated: rec.last_updated,
-                fqdn: fqdn.to_owned(),
-            }),
-            Err(e) => Err(e.into()),
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| (rec.source_id, rec.target_id, rec.count as u32))
+        .collect())
+    }
+}
+
+impl DomainLink {
+    #[tracing::instrument(skip(target_ids))]
+    /// get all referrers of the domains as `(target_id, source_id, links, total)` with aliases merged into their
+    /// canonical domain. `links` is the count of links to the domain and `total` the count of all links of the referrer.
+    pub async fn referrers(
+        pool: &DbPool,
+        target_ids: &[i32],
+    ) -> Result<Vec<(i32, i32, i64, i64)>, APIError> {
+        Ok(sqlx::query!(
+            r#"with links as (
+                   select coalesce(sa.canonical_id, l.source_id) as source_id,
+                          coalesce(ta.canonical_id, l.target_id) as target_id
+                   from domain_link l
+                   left join domain_aliases sa on sa.alias_id = l.source_id
+                   left join domain_aliases ta on ta.alias_id = l.target_id
+                   where coalesce(sa.canonical_id, l.source_id) <> coalesce(ta.canonical_id, l.target_id)
+               ), totals as (
+                   select source_id, count(*) as total
+                   from links
+                   group by source_id
+               )
+               select l.target_id as "target_id!", l.source_id as "source_id!",
+                      count(*) as "links!", t.total as "total!"
+               from links l
+               join totals t on t.source_id = l.source_id
+               where l.target_id = any($1)
+               group by l.target_id, l.source_id, t.total"#,
+            target_ids
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| (rec.target_id, rec.source_id, rec.links, rec.total))
+        .collect())
+    }
+}
+
+impl DomainAlias {
+    #[tracing::instrument]
+    /// Register `alias_id` as alias of `canonical_id`.
+    /// Aliases are always resolved to the final canonical domain to avoid alias chains: if the canonical domain is an
+    /// alias itself its canonical domain is used, and aliases of the new alias are moved over.
+    pub async fn upsert(pool: &DbPool, alias_id: i32, canonical_id: i32) -> Result<Self, APIError> {
+        let mut tx = pool.begin().await?;
+        let alias = Self::resolve(&mut tx, alias_id, canonical_id).await?;
+        tx.commit().await?;
+
+        Ok(alias)
+    }
+
+    #[tracing::instrument(skip(tx, records))]
+    /// register the canonical hosts of a batch of updates in the transaction writing the batch, so the sources are
+    /// scored as their canonical domain. The sources and canonical domains have to be written already.
+    /// Aliases are resolved one by one, see `DomainAlias::upsert`
+    pub async fn register(
+        tx: &mut Transaction<'_, Postgres>,
+        records: &[&UpdateRequest],
+    ) -> Result<(), APIError> {
+        let aliases: Vec<(String, String)> = records
+            .iter()
+            .filter_map(|record| {
+                record
+                    .canonical
+                    .as_ref()
+                    .map(|canonical| (trim(&record.fqdn), trim(canonical)))
+            })
+            .collect();
+        if aliases.is_empty() {
+            return Ok(());
+        }
+
+        let fqdns: Vec<String> = aliases
+            .iter()
+            .flat_map(|(alias, canonical)| [alias.clone(), canonical.clone()])
+            .collect();
+        let ids: HashMap<String, i32> = sqlx::query!(
+            r#"select id, fqdn from domains where fqdn = any($1)"#,
+            &fqdns
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|rec| (rec.fqdn, rec.id))
+        .collect();
+
+        for (alias, canonical) in aliases {
+            match (ids.get(&alias), ids.get(&canonical)) {
+                (Some(alias_id), Some(canonical_id)) => {
+                    Self::resolve(tx, *alias_id, *canonical_id).await?;
+                }
+                _ => return Err(APIError::NotFoundError),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// resolve and store an alias within a transaction, see `DomainAlias::upsert`
+    async fn resolve(
+        tx: &mut Transaction<'_, Postgres>,
+        alias_id: i32,
+        canonical_id: i32,
+    ) -> Result<Self, APIError> {
+        let mut resolved_id = sqlx::query!(
+            r#"select canonical_id from domain_aliases where alias_id = $1"#,
+            canonical_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .map_or(canonical_id, |rec| rec.canonical_id);
+
+        // the canonical domain was an alias of the new alias, e.g. after a redirect was flipped
+        if resolved_id == alias_id {
+            sqlx::query!(
+                r#"delete from domain_aliases where alias_id = $1"#,
+                canonical_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            resolved_id = canonical_id;
         }
+
+        sqlx::query!(
+            r#"update domain_aliases set canonical_id = $1 where canonical_id = $2"#,
+            resolved_id,
+            alias_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"insert into domain_aliases (alias_id, canonical_id)
+               values ($1, $2)
+               on conflict (alias_id)
+               do update set canonical_id = excluded.canonical_id"#,
+            alias_id,
+            resolved_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(Self {
+            alias_id,
+            canonical_id: resolved_id,
+        })
     }
+}
 
+impl Seed {
     #[tracing::instrument]
-    // doesn't update internal reference to avoid mutability
-    pub async fn refresh(&self, pool: &DbPool, last_updated: i64) -> Result<(), APIError> {
-        match sqlx::query!(
-            r#"update domains set last_updated = $1 where id = $2"#,
-            last_updated,
-            self.id
+    /// get all seeds with aliases resolved to their canonical domain
+    pub async fn all(pool: &DbPool) -> Result<Vec<Seed>, APIError> {
+        Ok(sqlx::query_as!(
+            Seed,
+            r#"select distinct coalesce(a.canonical_id, s.domain_id) as "domain_id!", s.network
+               from seeds s
+               left join domain_aliases a on a.alias_id = s.domain_id"#
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// get the seeds a run was started with
+    pub async fn by_run(pool: &DbPool, run_id: i32) -> Result<Vec<Seed>, APIError> {
+        Ok(sqlx::query_as!(
+            Seed,
+            r#"select domain_id, network from score_run_seeds where run_id = $1"#,
+            run_id
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    #[tracing::instrument(skip(seeds))]
+    /// store the seeds a run is started with, so runs can be compared later on
+    pub async fn snapshot(pool: &DbPool, run_id: i32, seeds: &[Seed]) -> Result<(), APIError> {
+        let domain_ids: Vec<i32> = seeds.iter().map(|seed| seed.domain_id).collect();
+        let networks: Vec<bool> = seeds.iter().map(|seed| seed.network).collect();
+
+        sqlx::query!(
+            r#"insert into score_run_seeds (run_id, domain_id, network)
+               select $1, * from unnest($2::int[], $3::bool[])"#,
+            run_id,
+            &domain_ids,
+            &networks
         )
         .execute(pool)
-        .await
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
-        }
+        .await?;
+
+        Ok(())
     }
 
     #[tracing::instrument]
-    pub async fn create(pool: &DbPool, last_updated: i64, fqdn: &str) -> Result<Self, APIError> {
-        let trimmed_fqdn = trim(fqdn);
+    pub async fn create(pool: &DbPool, domain_id: i32, network: bool) -> Result<Seed, APIError> {
+        sqlx::query!(
+            r#"insert into seeds (domain_id, network)
+               values ($1, $2)
+               on conflict on constraint seeds_pk
+               do nothing"#,
+            domain_id,
+            network
+        )
+        .execute(pool)
+        .await?;
 
-        match sqlx::query!(
-            r#"insert into domains (fqdn, fqdn_hash, last_updated) values ($1, $2, $3) returning id"#,
-            trimmed_fqdn,
-            SimpleDomain::hash(trimmed_fqdn.as_bytes()),
-            last_updated
-        ).fetch_one(pool).await {
-            Ok(rec) => Ok(SimpleDomain {
-                    id: rec.id,
-                    fqdn: trimmed_fqdn.to_owned(),
-                    last_updated
-                }),
-            Err(e) => Err(e.into())
+        Ok(Seed { domain_id, network })
+    }
+}
+
+impl ScoreRunState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Finished => "finished",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
         }
     }
+}
 
+impl ScoreRun {
     #[tracing::instrument]
-    // get latest_update_date by
-    pub async fn upsert(
-        pool: &DbPool,
-        fqdn: &str,
-        last_updated: i64,
-    ) -> Result<SimpleDomain, APIError> {
-        todo!()
+    pub async fn create(pool: &DbPool) -> Result<ScoreRun, APIError> {
+        Ok(sqlx::query_as!(
+            ScoreRun,
+            r#"insert into score_runs (state, started_at, heartbeat_at)
+               values ($1, $2, $2)
+               returning id, state, started_at, finished_at"#,
+            ScoreRunState::Running.as_str(),
+            timestamp()
+        )
+        .fetch_one(pool)
+        .await?)
     }
 
-    // bind to blake 3 hash
-    pub fn hash(fqdn: &[u8]) -> String {
-        blake3::hash(fqdn).to_string()
+    #[tracing::instrument]
+    pub async fn get(pool: &DbPool, id: i32) -> Result<ScoreRun, APIError> {
+        Ok(sqlx::query_as!(
+            ScoreRun,
+            r#"select id, state, started_at, finished_at from score_runs where id = $1"#,
+            id
+        )
+        .fetch_one(pool)
+        .await?)
     }
 
     #[tracing::instrument]
-    pub async fn by_hash(
-        pool: &DbPool,
-        hash: &String,
-        per_page: u32,
-        page: u32,
-    ) -> Result<Vec<DomainResponse>, APIError> {
-        Ok(sqlx::query!(
-            r#"select fqdn, last_updated
-        from domains
-        where fqdn_hash like concat($1::text, '%')
-        limit $2
-        offset $3"#,
-            trim(hash),
-            per_page as i64,
-            (per_page * page) as i64
+    /// get the latest finished run. Its scores are the ones published to clients
+    pub async fn latest(pool: &DbPool) -> Result<Option<ScoreRun>, APIError> {
+        Ok(sqlx::query_as!(
+            ScoreRun,
+            r#"select id, state, started_at, finished_at
+               from score_runs
+               where state = $1
+               order by id desc
+               limit 1"#,
+            ScoreRunState::Finished.as_str()
         )
-        .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|rec| DomainResponse {
-            fqdn: rec.fqdn,
-            score: [0.0, 0.0],
-            last_updated: rec.last_updated,
-        })
-        .collect())
+        .fetch_optional(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    // doesn't update internal reference to avoid mutability
+    pub async fn finish(&self, pool: &DbPool, state: ScoreRunState) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"update score_runs set state = $1, finished_at = $2 where id = $3"#,
+            state.as_str(),
+            timestamp(),
+            self.id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    /// signal that the run is still executed, see [`ScoreRun::fail_interrupted`]
+    pub async fn heartbeat(&self, pool: &DbPool) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"update score_runs set heartbeat_at = $1 where id = $2 and state = $3"#,
+            timestamp(),
+            self.id,
+            ScoreRunState::Running.as_str()
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    /// mark runs that were interrupted by a shutdown of their backend as failed. Runs of other instances
+    /// are still running as long as their heartbeat isn't older than `timeout` seconds
+    pub async fn fail_interrupted(pool: &DbPool, timeout: u64) -> Result<PgQueryResult, APIError> {
+        let now = timestamp();
+
+        Ok(sqlx::query!(
+            r#"update score_runs set state = $1, finished_at = $2
+               where state = $3 and heartbeat_at < $4"#,
+            ScoreRunState::Failed.as_str(),
+            now,
+            ScoreRunState::Running.as_str(),
+            now - timeout as i64
+        )
+        .execute(pool)
+        .await?)
+    }
+}
+
+impl DomainScore {
+    #[tracing::instrument]
+    pub async fn by_run(pool: &DbPool, run_id: i32) -> Result<Vec<DomainScore>, APIError> {
+        Ok(sqlx::query!(
+            r#"select domain_id, score_true, score_false from domain_scores where run_id = $1"#,
+            run_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| DomainScore {
+            domain_id: rec.domain_id,
+            score: [rec.score_true, rec.score_false],
+        })
+        .collect())
+    }
+
+    #[tracing::instrument]
+    /// get the scores of a run grouped by the first `prefix_length` characters of the fqdn hash.
+    /// Aliases are included with the scores of their canonical domain
+    pub async fn by_prefix(
+        pool: &DbPool,
+        run_id: i32,
+        prefix_length: usize,
+    ) -> Result<Vec<PrefixScore>, APIError> {
+        Ok(sqlx::query!(
+            r#"select left(d.fqdn_hash, $2) as "prefix!",
+            max(s.score_true) as "score_true!", max(s.score_false) as "score_false!"
+        from domains d
+        left join domain_aliases a on a.alias_id = d.id
+        join domain_scores s on s.domain_id = coalesce(a.canonical_id, d.id) and s.run_id = $1
+        group by 1
+        order by 1"#,
+            run_id,
+            prefix_length as i32
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| PrefixScore {
+            prefix: rec.prefix,
+            score: [rec.score_true, rec.score_false],
+        })
+        .collect())
+    }
+
+    #[tracing::instrument(skip(scores))]
+    /// insert the scores of a run in batches with a single multi-row insert per batch
+    pub async fn insert_many(
+        pool: &DbPool,
+        run_id: i32,
+        scores: &[DomainScore],
+    ) -> Result<(), APIError> {
+        for batch in scores.chunks(10_000) {
+            let domain_ids: Vec<i32> = batch.iter().map(|score| score.domain_id).collect();
+            let scores_true: Vec<f32> = batch.iter().map(|score| score.score[0]).collect();
+            let scores_false: Vec<f32> = batch.iter().map(|score| score.score[1]).collect();
+
+            sqlx::query!(
+                r#"insert into domain_scores (run_id, domain_id, score_true, score_false)
+                   select $1, * from unnest($2::int[], $3::real[], $4::real[])"#,
+                run_id,
+                &domain_ids,
+                &scores_true,
+                &scores_false
+            )
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PrefixList {
+    #[tracing::instrument(skip(entries))]
+    /// publish the prefix list of a run. Only the latest `retained` lists are kept for delta updates
+    pub async fn create(
+        pool: &DbPool,
+        run_id: i32,
+        prefix_length: i32,
+        bands: &[f32],
+        entries: &[PrefixEntry],
+        retained: i64,
+    ) -> Result<PrefixList, APIError> {
+        let mut tx = pool.begin().await?;
+
+        let list = sqlx::query_as!(
+            PrefixList,
+            r#"insert into prefix_lists (run_id, prefix_length, bands, published_at)
+               values ($1, $2, $3, $4)
+               returning run_id, prefix_length, bands, published_at"#,
+            run_id,
+            prefix_length,
+            bands,
+            timestamp()
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        for batch in entries.chunks(10_000) {
+            let prefixes: Vec<String> = batch.iter().map(|entry| entry.0.clone()).collect();
+            let bands_true: Vec<i16> = batch.iter().map(|entry| entry.1[0] as i16).collect();
+            let bands_false: Vec<i16> = batch.iter().map(|entry| entry.1[1] as i16).collect();
+
+            sqlx::query!(
+                r#"insert into prefix_list_entries (run_id, prefix, band_true, band_false)
+                   select $1, * from unnest($2::text[], $3::smallint[], $4::smallint[])"#,
+                run_id,
+                &prefixes,
+                &bands_true,
+                &bands_false
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"delete from prefix_lists where run_id not in (
+                select run_id from prefix_lists order by run_id desc limit $1
+            )"#,
+            retained
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(list)
+    }
+
+    #[tracing::instrument]
+    pub async fn get(pool: &DbPool, run_id: i32) -> Result<Option<PrefixList>, APIError> {
+        Ok(sqlx::query_as!(
+            PrefixList,
+            r#"select run_id, prefix_length, bands, published_at from prefix_lists where run_id = $1"#,
+            run_id
+        )
+        .fetch_optional(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// get the latest published list
+    pub async fn latest(pool: &DbPool) -> Result<Option<PrefixList>, APIError> {
+        Ok(sqlx::query_as!(
+            PrefixList,
+            r#"select run_id, prefix_length, bands, published_at
+               from prefix_lists
+               order by run_id desc
+               limit 1"#
+        )
+        .fetch_optional(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    pub async fn entries(&self, pool: &DbPool) -> Result<Vec<PrefixEntry>, APIError> {
+        Ok(sqlx::query!(
+            r#"select prefix, band_true, band_false
+               from prefix_list_entries
+               where run_id = $1
+               order by prefix"#,
+            self.run_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| PrefixEntry(rec.prefix, [rec.band_true as u8, rec.band_false as u8]))
+        .collect())
+    }
+
+    #[tracing::instrument]
+    /// get the entries that were added, changed or removed between the lists of `since` and `version`
+    pub async fn changes(
+        pool: &DbPool,
+        since: i32,
+        version: i32,
+    ) -> Result<Vec<PrefixChange>, APIError> {
+        Ok(sqlx::query!(
+            r#"select coalesce(n.prefix, o.prefix) as "prefix!", n.band_true as "band_true?",
+            n.band_false as "band_false?", o.prefix is null as "added!"
+        from (select * from prefix_list_entries where run_id = $1) o
+        full join (select * from prefix_list_entries where run_id = $2) n on n.prefix = o.prefix
+        where o.prefix is null or n.prefix is null
+            or n.band_true <> o.band_true or n.band_false <> o.band_false
+        order by 1"#,
+            since,
+            version
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| PrefixChange {
+            prefix: rec.prefix,
+            bands: rec
+                .band_true
+                .zip(rec.band_false)
+                .map(|(band_true, band_false)| [band_true as u8, band_false as u8]),
+            added: rec.added,
+        })
+        .collect())
+    }
+}
+
+impl CrawlSession {
+    #[tracing::instrument]
+    /// open a new session. Sessions exceeding `ttl` seconds are discarded
+    pub async fn create(
+        pool: &DbPool,
+        fqdn: &str,
+        network: bool,
+        last_updated: i64,
+        ttl: i64,
+        scraper_id: Option<i32>,
+    ) -> Result<CrawlSession, APIError> {
+        sqlx::query!(
+            r#"delete from crawl_sessions where created_at < $1"#,
+            timestamp() - ttl
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(sqlx::query_as!(
+            CrawlSession,
+            r#"insert into crawl_sessions (fqdn, network, last_updated, created_at, scraper_id)
+               values ($1, $2, $3, $4, $5)
+               returning id, fqdn, network, last_updated, created_at, scraper_id"#,
+            trim(fqdn),
+            network,
+            last_updated,
+            timestamp(),
+            scraper_id
+        )
+        .fetch_one(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// get an open session that didn't exceed `ttl` seconds
+    pub async fn get(pool: &DbPool, id: i32, ttl: i64) -> Result<CrawlSession, APIError> {
+        Ok(sqlx::query_as!(
+            CrawlSession,
+            r#"select id, fqdn, network, last_updated, created_at, scraper_id
+               from crawl_sessions
+               where id = $1 and created_at >= $2"#,
+            id,
+            timestamp() - ttl
+        )
+        .fetch_one(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// count of links staged in the session
+    pub async fn link_count(&self, pool: &DbPool) -> Result<i64, APIError> {
+        Ok(sqlx::query!(
+            r#"select count(*) as "count!" from crawl_session_links where session_id = $1"#,
+            self.id
+        )
+        .fetch_one(pool)
+        .await?
+        .count)
+    }
+
+    #[tracing::instrument(skip(links))]
+    /// stage a batch of links, links staged before are ignored
+    pub async fn add_links(&self, pool: &DbPool, links: &[String]) -> Result<(), APIError> {
+        let links: Vec<String> = links.iter().map(|link| trim(link)).collect();
+
+        sqlx::query!(
+            r#"insert into crawl_session_links (session_id, fqdn)
+               select $1::int, * from unnest($2::text[])
+               on conflict on constraint csl_pk
+               do nothing"#,
+            self.id,
+            &links
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    /// replace the links of the source with the staged links in a single transaction and close the session.
+    /// Sessions of a crawl older than the last update of the source are closed without changing its links.
+    /// Returns the count of links of the source
+    pub async fn commit(&self, pool: &DbPool) -> Result<u64, APIError> {
+        let mut tx = pool.begin().await?;
+
+        // locking the session waits for batches currently staged and blocks further ones, since staging a link
+        // locks the session through its foreign key. It also locks the session against a concurrent commit or abort
+        if sqlx::query!(
+            r#"select id from crawl_sessions where id = $1 for update"#,
+            self.id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .is_none()
+        {
+            return Err(APIError::NotFoundError);
+        }
+
+        let links: Vec<String> = sqlx::query!(
+            r#"select fqdn from crawl_session_links where session_id = $1"#,
+            self.id
+        )
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map(|rec| rec.fqdn)
+        .collect();
+
+        sqlx::query!(r#"delete from crawl_sessions where id = $1"#, self.id)
+            .execute(&mut tx)
+            .await?;
+
+        // like `/update`, an older crawl must not replace the links of a newer one
+        if let Some(rec) = sqlx::query!(
+            r#"select last_updated from domains where fqdn = $1 for update"#,
+            self.fqdn
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        {
+            if rec.last_updated > self.last_updated {
+                tx.commit().await?;
+
+                return Err(APIError::ConflictError(format!(
+                    "{} was already updated with a newer crawl from {}",
+                    self.fqdn, rec.last_updated
+                )));
+            }
+        }
+
+        let source_id = sqlx::query!(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated, crawled)
+               values ($1, $2, $3, $4, true)
+               on conflict (fqdn)
+               do update set
+                   network = case when excluded.last_updated >= domains.last_updated
+                       then excluded.network else domains.network end,
+                   last_updated = greatest(domains.last_updated, excluded.last_updated),
+                   crawled = true
+               returning id"#,
+            self.fqdn,
+            SimpleDomain::hash(self.fqdn.as_bytes()),
+            self.network,
+            self.last_updated
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .id;
+
+        let hashes: Vec<String> = links
+            .iter()
+            .map(|link| SimpleDomain::hash(link.as_bytes()))
+            .collect();
+        sqlx::query!(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated)
+               select *, $3::boolean, $4::bigint from unnest($1::text[], $2::text[])
+               on conflict (fqdn)
+               do nothing"#,
+            &links,
+            &hashes,
+            self.network,
+            self.last_updated
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"delete from crawl_frontier where domain_id = $1"#,
+            source_id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // links that are still reported keep the reports of other scrapers
+        sqlx::query!(
+            r#"delete from domain_link l
+               where l.source_id = $1
+                     and not exists (select 1 from domains d where d.id = l.target_id and d.fqdn = any($2))"#,
+            source_id,
+            &links
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"insert into domain_link (source_id, target_id)
+               select $1, id from domains where fqdn = any($2) and id <> $1
+               on conflict on constraint dl_pk
+               do nothing"#,
+            source_id,
+            &links
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // the links are received with the commit, every link has exactly one report of the scraper
+        let inserted = sqlx::query!(
+            r#"insert into link_reports (source_id, target_id, scraper_id, first_reported_at, last_reported_at)
+               select $1, id, $3, $4, $4 from domains where fqdn = any($2) and id <> $1
+               on conflict (source_id, target_id, coalesce(scraper_id, 0))
+               do update set last_reported_at = greatest(link_reports.last_reported_at, excluded.last_reported_at)"#,
+            source_id,
+            &links,
+            self.scraper_id,
+            timestamp()
+        )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(inserted)
+    }
+
+    #[tracing::instrument]
+    /// discard a session with all staged links
+    pub async fn abort(pool: &DbPool, id: i32) -> Result<(), APIError> {
+        match sqlx::query!(r#"delete from crawl_sessions where id = $1"#, id)
+            .execute(pool)
+            .await?
+            .rows_affected()
+        {
+            0 => Err(APIError::NotFoundError),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl CrawlFrontier {
+    #[tracing::instrument(skip(config))]
+    /// queue up to `config.refill_size` domains that are due for crawling, high-value domains first, then
+    /// unseen ones and the stalest ones last. Queued domains keep their lease. Returns the count of queued domains
+    pub async fn refill(
+        pool: &DbPool,
+        run_id: Option<i32>,
+        config: &FrontierConfig,
+    ) -> Result<u64, APIError> {
+        let now = timestamp();
+
+        Ok(sqlx::query!(
+            r#"insert into crawl_frontier (domain_id, priority, reason, queued_at)
+               select id, priority, reason, $1
+               from (
+                   select d.id, d.last_updated,
+                          case when v.id is not null then 2 when not d.crawled then 1 else 0 end as priority,
+                          case when v.id is not null then 'value'
+                               when not d.crawled then 'unseen'
+                               else 'stale' end as reason
+                   from domains d
+                   left join (
+                       select domain_id as id from seeds
+                       union
+                       select l.target_id from domain_link l join seeds s on s.domain_id = l.source_id
+                       union
+                       select domain_id from domain_scores
+                       where run_id = $2 and greatest(score_true, score_false) >= $3
+                   ) v on v.id = d.id
+                   where not d.crawled
+                         or d.last_updated < $1 - $4::bigint
+                         or (v.id is not null and d.last_updated < $1 - $5::bigint)
+               ) due
+               order by priority desc, last_updated
+               limit $6
+               on conflict (domain_id)
+               do update set priority = excluded.priority, reason = excluded.reason"#,
+            now,
+            run_id,
+            config.min_score,
+            config.stale_after,
+            config.value_stale_after,
+            config.refill_size
+        )
+        .execute(pool)
+        .await?
+        .rows_affected())
+    }
+
+    #[tracing::instrument]
+    /// lease up to `count` queued domains for `timeout` seconds. Domains with an expired lease are leased again
+    pub async fn lease(
+        pool: &DbPool,
+        count: i64,
+        timeout: i64,
+    ) -> Result<Vec<FrontierItem>, APIError> {
+        let now = timestamp();
+
+        // skipping locked rows keeps concurrent leases from handing out the same domain
+        Ok(sqlx::query_as!(
+            FrontierItem,
+            r#"with leased as (
+                   update crawl_frontier f
+                   set leased_until = $1 + $2
+                   where f.domain_id in (
+                       select domain_id from crawl_frontier
+                       where leased_until is null or leased_until <= $1
+                       order by priority desc, queued_at
+                       limit $3
+                       for update skip locked
+                   )
+                   returning f.domain_id, f.reason, f.leased_until
+               )
+               select d.fqdn as "fqdn!", d.network as "network!", l.reason as "reason!",
+                   l.leased_until as "leased_until!"
+               from leased l
+               join domains d on d.id = l.domain_id"#,
+            now,
+            timeout,
+            count
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// remove a crawled domain from the frontier, whether it was leased or not
+    pub async fn ack(pool: &DbPool, domain_id: i32) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"delete from crawl_frontier where domain_id = $1"#,
+            domain_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl IdempotencyKey {
+    #[tracing::instrument]
+    /// claim `key` of a scraper for a request for `lease` seconds. Returns `None` if the key was claimed, otherwise the
+    /// stored key. A claim without result whose lease expired is taken over by a request with the same fingerprint, so
+    /// a crashed request can be retried. Keys older than `retention` seconds are discarded first
+    pub async fn claim(
+        pool: &DbPool,
+        key: &str,
+        scraper_id: Option<i32>,
+        fingerprint: &str,
+        retention: i64,
+        lease: i64,
+    ) -> Result<Option<IdempotencyKey>, APIError> {
+        let now = timestamp();
+
+        sqlx::query!(
+            r#"delete from idempotency_keys where created_at < $1"#,
+            now - retention
+        )
+        .execute(pool)
+        .await?;
+
+        let claimed = sqlx::query!(
+            r#"insert into idempotency_keys (key, scraper_id, fingerprint, created_at, leased_until)
+               values ($1, $2, $3, $4, $5)
+               on conflict (coalesce(scraper_id, 0), key)
+               do update set created_at = excluded.created_at, leased_until = excluded.leased_until
+               where idempotency_keys.status is null
+                     and idempotency_keys.leased_until < excluded.created_at
+                     and idempotency_keys.fingerprint = excluded.fingerprint"#,
+            key,
+            scraper_id,
+            fingerprint,
+            now,
+            now + lease
+        )
+        .execute(pool)
+        .await?
+        .rows_affected()
+            == 1;
+        if claimed {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            sqlx::query_as!(
+                IdempotencyKey,
+                r#"select key, fingerprint, status, body, created_at
+                   from idempotency_keys
+                   where coalesce(scraper_id, 0) = coalesce($2::int, 0) and key = $1"#,
+                key,
+                scraper_id
+            )
+            .fetch_one(pool)
+            .await?,
+        ))
+    }
+
+    #[tracing::instrument(skip(body))]
+    /// store the result of the request `key` was claimed for
+    pub async fn complete(
+        pool: &DbPool,
+        key: &str,
+        scraper_id: Option<i32>,
+        status: i16,
+        body: Option<String>,
+    ) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"update idempotency_keys set status = $3, body = $4
+               where coalesce(scraper_id, 0) = coalesce($2::int, 0) and key = $1"#,
+            key,
+            scraper_id,
+            status,
+            body
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    /// release a claimed key without result, so the request can be retried
+    pub async fn release(
+        pool: &DbPool,
+        key: &str,
+        scraper_id: Option<i32>,
+    ) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"delete from idempotency_keys
+               where coalesce(scraper_id, 0) = coalesce($2::int, 0) and key = $1"#,
+            key,
+            scraper_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl QuarantinedPayload {
+    #[tracing::instrument(skip(payloads, reasons, locs, config))]
+    /// quarantine payloads with the reason they were rejected for. `locs` holds an empty string if the
+    /// rejection didn't point at a field. Payloads older than `config.retention` seconds are discarded first, payloads
+    /// of a scraper already holding `config.max_payloads` aren't quarantined. Returns the count of quarantined payloads
+    pub async fn create(
+        pool: &DbPool,
+        payloads: &[String],
+        reasons: &[String],
+        locs: &[String],
+        scraper_id: Option<i32>,
+        source: &str,
+        config: &QuarantineConfig,
+    ) -> Result<u64, APIError> {
+        let now = timestamp();
+
+        sqlx::query!(
+            r#"delete from quarantined_payloads where quarantined_at < $1"#,
+            now - config.retention
+        )
+        .execute(pool)
+        .await?;
+
+        // the quota is checked once per batch, a batch may exceed it
+        Ok(sqlx::query!(
+            r#"insert into quarantined_payloads (payload, reason, loc, scraper_id, source, quarantined_at)
+               select p.payload, p.reason, nullif(p.loc, ''), $4, $5, $6
+               from unnest($1::text[], $2::text[], $3::text[]) p(payload, reason, loc)
+               where (
+                   select count(*) from quarantined_payloads where scraper_id is not distinct from $4
+               ) < $7"#,
+            payloads,
+            reasons,
+            locs,
+            scraper_id,
+            source,
+            now,
+            config.max_payloads
+        )
+        .execute(pool)
+        .await?
+        .rows_affected())
+    }
+
+    #[tracing::instrument]
+    pub async fn get(pool: &DbPool, id: i32) -> Result<QuarantinedPayload, APIError> {
+        Ok(sqlx::query_as!(
+            QuarantinedPayload,
+            r#"select id, payload, reason, loc, scraper_id, source, quarantined_at
+               from quarantined_payloads
+               where id = $1"#,
+            id
+        )
+        .fetch_one(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// get a page of quarantined payloads, oldest first, optionally only the ones of a single scraper
+    pub async fn list(
+        pool: &DbPool,
+        scraper_id: Option<i32>,
+        per_page: u32,
+        page: u32,
+    ) -> Result<Vec<QuarantinedPayload>, APIError> {
+        Ok(sqlx::query_as!(
+            QuarantinedPayload,
+            r#"select id, payload, reason, loc, scraper_id, source, quarantined_at
+               from quarantined_payloads
+               where $1::int is null or scraper_id = $1
+               order by id
+               limit $2 offset $3"#,
+            scraper_id,
+            per_page as i64,
+            offset(per_page, page, None)
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    #[tracing::instrument]
+    /// store the reason a payload is still rejected for, e.g. after the validation rules changed
+    pub async fn update_reason(
+        pool: &DbPool,
+        id: i32,
+        reason: &str,
+        loc: Option<&str>,
+    ) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"update quarantined_payloads set reason = $2, loc = $3 where id = $1"#,
+            id,
+            reason,
+            loc
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    pub async fn delete(pool: &DbPool, id: i32) -> Result<(), APIError> {
+        let deleted = sqlx::query!(r#"delete from quarantined_payloads where id = $1"#, id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+
+        match deleted {
+            0 => Err(APIError::NotFoundError),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl IngestQueueEntry {
+    #[tracing::instrument(skip(payload))]
+    pub async fn push(
+        pool: &DbPool,
+        payload: &str,
+        scraper_id: Option<i32>,
+    ) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"insert into ingest_queue (payload, scraper_id, enqueued_at) values ($1, $2, $3)"#,
+            payload,
+            scraper_id,
+            timestamp()
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    /// lease the `count` oldest entries for `timeout` seconds. Entries that weren't acknowledged in time,
+    /// e.g. because the instance writing them stopped, are leased again
+    pub async fn lease(
+        pool: &DbPool,
+        count: i64,
+        timeout: i64,
+    ) -> Result<Vec<IngestQueueEntry>, APIError> {
+        let now = timestamp();
+
+        Ok(sqlx::query_as!(
+            IngestQueueEntry,
+            r#"update ingest_queue
+               set leased_until = $1 + $2
+               where id in (
+                   select id from ingest_queue
+                   where leased_until is null or leased_until <= $1
+                   order by id
+                   limit $3
+                   for update skip locked
+               )
+               returning id, payload, scraper_id, enqueued_at"#,
+            now,
+            timeout,
+            count
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    #[tracing::instrument(skip(ids))]
+    /// remove written entries from the queue
+    pub async fn ack(pool: &DbPool, ids: &[i64]) -> Result<(), APIError> {
+        sqlx::query!(r#"delete from ingest_queue where id = any($1)"#, ids)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    /// count of queued entries, including leased ones
+    pub async fn depth(pool: &DbPool) -> Result<i64, APIError> {
+        Ok(
+            sqlx::query!(r#"select count(*) as "depth!" from ingest_queue"#)
+                .fetch_one(pool)
+                .await?
+                .depth,
+        )
+    }
+}
+
+impl Import {
+    #[tracing::instrument]
+    /// last line of `file` that was imported, 0 if the import wasn't started yet
+    pub async fn progress(pool: &DbPool, file: &str) -> Result<i64, APIError> {
+        Ok(
+            sqlx::query!(r#"select line from imports where file = $1"#, file)
+                .fetch_optional(pool)
+                .await?
+                .map_or(0, |rec| rec.line),
+        )
+    }
+
+    #[tracing::instrument]
+    /// record `line` as the last imported line of `file`
+    pub async fn advance(pool: &DbPool, file: &str, line: i64) -> Result<(), APIError> {
+        sqlx::query!(
+            r#"insert into imports (file, line, updated_at)
+               values ($1, $2, $3)
+               on conflict (file)
+               do update set line = excluded.line, updated_at = excluded.updated_at"#,
+            file,
+            line,
+            timestamp()
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(records))]
+    /// load a batch of updates with `COPY` into staging tables and merge them into the graph in a single
+    /// transaction, with the same semantics as `SimpleDomain::ingest`. Loading a batch again doesn't change the graph.
+    pub async fn load(pool: &DbPool, records: &[UpdateRequest]) -> Result<(), APIError> {
+        let mut tx = pool.begin().await?;
+        let records = fresh(&mut tx, records).await?;
+
+        let mut domains = String::new();
+        let mut links = String::new();
+
+        for (position, record) in records.iter().enumerate() {
+            let fqdn = trim(&record.fqdn);
+            let position = position.to_string();
+            let network = record.network.to_string();
+            let last_updated = record.last_updated.to_string();
+
+            for target in record.links.iter().chain(&record.canonical) {
+                let target = trim(target);
+                let hash = SimpleDomain::hash(target.as_bytes());
+                copy_row(
+                    &mut domains,
+                    &[&position, &target, &hash, &network, &last_updated, "f"],
+                );
+            }
+            for link in &record.links {
+                copy_row(&mut links, &[&fqdn, &trim(link)]);
+            }
+
+            let hash = SimpleDomain::hash(fqdn.as_bytes());
+            copy_row(
+                &mut domains,
+                &[&position, &fqdn, &hash, &network, &last_updated, "t"],
+            );
+        }
+
+        // the staging tables only exist within this transaction and can't be checked at compile time
+        sqlx::query(
+            r#"create temporary table import_domains (
+                   position INT, fqdn TEXT, fqdn_hash TEXT, network BOOLEAN, last_updated BIGINT, source BOOLEAN
+               ) on commit drop"#,
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            r#"create temporary table import_links (source TEXT, target TEXT) on commit drop"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        let mut copy = tx.copy_in_raw(r#"copy import_domains from stdin"#).await?;
+        copy.send(domains.into_bytes()).await?;
+        copy.finish().await?;
+
+        let mut copy = tx.copy_in_raw(r#"copy import_links from stdin"#).await?;
+        copy.send(links.into_bytes()).await?;
+        copy.finish().await?;
+
+        // the newest update of a source wins
+        sqlx::query(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated, crawled)
+               select distinct on (fqdn) fqdn, fqdn_hash, network, last_updated, true
+               from import_domains
+               where source
+               order by fqdn, last_updated desc, position desc
+               on conflict (fqdn)
+               do update set
+                   network = case when excluded.last_updated >= domains.last_updated
+                       then excluded.network else domains.network end,
+                   last_updated = greatest(domains.last_updated, excluded.last_updated),
+                   crawled = true"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"delete from crawl_frontier f
+               using import_domains i, domains d
+               where i.source and d.fqdn = i.fqdn and f.domain_id = d.id"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated)
+               select distinct on (fqdn) fqdn, fqdn_hash, network, last_updated
+               from import_domains
+               where not source
+               order by fqdn, position
+               on conflict (fqdn)
+               do nothing"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"insert into domain_link (source_id, target_id)
+               select s.id, t.id
+               from import_links l
+               join domains s on s.fqdn = l.source
+               join domains t on t.fqdn = l.target
+               on conflict on constraint dl_pk
+               do nothing"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // imported links aren't attributed to any scraper
+        sqlx::query(
+            r#"insert into link_reports (source_id, target_id, scraper_id, first_reported_at, last_reported_at)
+               select distinct s.id, t.id, null::int, $1::bigint, $1::bigint
+               from import_links l
+               join domains s on s.fqdn = l.source
+               join domains t on t.fqdn = l.target
+               on conflict (source_id, target_id, coalesce(scraper_id, 0))
+               do update set last_reported_at = greatest(link_reports.last_reported_at, excluded.last_reported_at)"#,
+        )
+        .bind(timestamp())
+        .execute(&mut tx)
+        .await?;
+
+        DomainAlias::register(&mut tx, &records).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl SimpleDomain {
+    #[tracing::instrument(skip(records))]
+    /// bulk variant of `/update` writing a batch of updates with multi-row inserts in a single transaction.
+    /// Domains are written with the same semantics as `SimpleDomain::upsert`. Unknown linked and canonical domains
+    /// are created with the network of the first update referencing them. Canonical hosts are registered within the
+    /// same transaction, see `DomainAlias::register`. Links are reported by `scraper_id` as received at `received_at`.
+    /// Like `/update`, stale updates are skipped with all of their links, see `fresh`.
+    pub async fn ingest(
+        pool: &DbPool,
+        records: &[UpdateRequest],
+        scraper_id: Option<i32>,
+        received_at: i64,
+    ) -> Result<(), APIError> {
+        let mut tx = pool.begin().await?;
+        let records = fresh(&mut tx, records).await?;
+
+        let mut sources: HashMap<String, (bool, i64)> = HashMap::new();
+        let mut targets: HashMap<String, (bool, i64)> = HashMap::new();
+        let mut link_sources = vec![];
+        let mut link_targets = vec![];
+
+        for record in &records {
+            let fqdn = trim(&record.fqdn);
+
+            for target in record.links.iter().chain(&record.canonical) {
+                targets
+                    .entry(trim(target))
+                    .or_insert((record.network, record.last_updated));
+            }
+            for link in &record.links {
+                link_sources.push(fqdn.clone());
+                link_targets.push(trim(link));
+            }
+
+            // the fresh updates of a source are ordered, the last one is the newest
+            sources.insert(fqdn, (record.network, record.last_updated));
+        }
+        targets.retain(|fqdn, _| !sources.contains_key(fqdn));
+
+        // every source occurs only once, postgres can't update the same row twice in a single insert
+        let (fqdns, hashes, networks, last_updated) = domain_columns(sources);
+        sqlx::query!(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated, crawled)
+               select *, true from unnest($1::text[], $2::text[], $3::boolean[], $4::bigint[])
+               on conflict (fqdn)
+               do update set
+                   network = case when excluded.last_updated >= domains.last_updated
+                       then excluded.network else domains.network end,
+                   last_updated = greatest(domains.last_updated, excluded.last_updated),
+                   crawled = true"#,
+            &fqdns,
+            &hashes,
+            &networks,
+            &last_updated
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"delete from crawl_frontier f
+               using domains d
+               where d.fqdn = any($1) and f.domain_id = d.id"#,
+            &fqdns
+        )
+        .execute(&mut tx)
+        .await?;
+
+        let (fqdns, hashes, networks, last_updated) = domain_columns(targets);
+        sqlx::query!(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated)
+               select * from unnest($1::text[], $2::text[], $3::boolean[], $4::bigint[])
+               on conflict (fqdn)
+               do nothing"#,
+            &fqdns,
+            &hashes,
+            &networks,
+            &last_updated
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"insert into domain_link (source_id, target_id)
+               select s.id, t.id
+               from unnest($1::text[], $2::text[]) l(source, target)
+               join domains s on s.fqdn = l.source
+               join domains t on t.fqdn = l.target
+               on conflict on constraint dl_pk
+               do nothing"#,
+            &link_sources,
+            &link_targets
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // postgres can't update the same row twice in a single insert, hence repeated links are reported once
+        sqlx::query!(
+            r#"insert into link_reports (source_id, target_id, scraper_id, first_reported_at, last_reported_at)
+               select distinct s.id, t.id, $3::int, $4::bigint, $4::bigint
+               from unnest($1::text[], $2::text[]) l(source, target)
+               join domains s on s.fqdn = l.source
+               join domains t on t.fqdn = l.target
+               on conflict (source_id, target_id, coalesce(scraper_id, 0))
+               do update set last_reported_at = greatest(link_reports.last_reported_at, excluded.last_reported_at)"#,
+            &link_sources,
+            &link_targets,
+            scraper_id,
+            received_at
+        )
+        .execute(&mut tx)
+        .await?;
+
+        DomainAlias::register(&mut tx, &records).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl SimpleDomain {
+    #[tracing::instrument]
+    pub async fn get_by_fqdn(pool: &DbPool, fqdn: &str) -> Result<SimpleDomain, APIError> {
+        match sqlx::query!(
+            r#"
+        select last_updated, id from domains where fqdn = $1
+        "#,
+            fqdn
+        )
+        .fetch_one(pool)
+        .await
+        {
+            Ok(rec) => Ok(SimpleDomain {
+                id: rec.id,
+                last_updated: rec.last_updated,
+                fqdn: fqdn.to_owned(),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[tracing::instrument]
+    /// insert a domain or update the existing one in a single atomic statement, so concurrent updates can't race.
+    /// Sources of an update (`source = true`) are authoritative: they mark the domain as crawled, set its network
+    /// and advance `last_updated`, which never moves backwards. Stale updates don't change the domain, callers detect them by
+    /// the returned `last_updated` being newer than the supplied one. Linked and canonical domains only take the network and
+    /// `last_updated` of the referencing update when they are created.
+    pub async fn upsert(
+        pool: &DbPool,
+        fqdn: &str,
+        network: bool,
+        last_updated: i64,
+        source: bool,
+    ) -> Result<SimpleDomain, APIError> {
+        let fqdn = trim(fqdn);
+
+        // `do nothing` wouldn't return the existing row, hence targets are updated with their own values
+        let rec = sqlx::query!(
+            r#"insert into domains (fqdn, fqdn_hash, network, last_updated, crawled)
+               values ($1, $2, $3, $4, $5)
+               on conflict (fqdn)
+               do update set
+                   network = case when $5 and excluded.last_updated >= domains.last_updated
+                       then excluded.network else domains.network end,
+                   last_updated = case when $5
+                       then greatest(domains.last_updated, excluded.last_updated)
+                       else domains.last_updated end,
+                   crawled = domains.crawled or $5
+               returning id, last_updated"#,
+            fqdn,
+            SimpleDomain::hash(fqdn.as_bytes()),
+            network,
+            last_updated,
+            source
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(SimpleDomain {
+            id: rec.id,
+            fqdn,
+            last_updated: rec.last_updated,
+        })
+    }
+
+    #[tracing::instrument(skip(ids))]
+    /// get the fqdns of multiple domains by their id
+    pub async fn fqdns(pool: &DbPool, ids: &[i32]) -> Result<HashMap<i32, String>, APIError> {
+        Ok(
+            sqlx::query!(r#"select id, fqdn from domains where id = any($1)"#, ids)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|rec| (rec.id, rec.fqdn))
+                .collect(),
+        )
+    }
+
+    // bind to blake 3 hash
+    pub fn hash(fqdn: &[u8]) -> String {
+        blake3::hash(fqdn).to_string()
+    }
+
+    #[tracing::instrument]
+    /// get domains following a random position in the hash space, used as decoys in padded responses
+    pub async fn decoys(
+        pool: &DbPool,
+        start: &str,
+        count: u32,
+    ) -> Result<Vec<DomainResponse>, APIError> {
+        Ok(sqlx::query!(
+            r#"select d.fqdn as "fqdn!", coalesce(c.last_updated, d.last_updated) as "last_updated!", c.fqdn as "canonical?",
+            coalesce(s.score_true, 0) as "score_true!", coalesce(s.score_false, 0) as "score_false!"
+        from (
+            (select * from domains where fqdn_hash >= $1 order by fqdn_hash limit $2)
+            union all
+            (select * from domains where fqdn_hash < $1 order by fqdn_hash limit $2)
+        ) d
+        left join domain_aliases a on a.alias_id = d.id
+        left join domains c on c.id = a.canonical_id
+        left join domain_scores s on s.domain_id = coalesce(a.canonical_id, d.id)
+            and s.run_id = (select max(id) from score_runs where state = 'finished')
+        limit $2"#,
+            start,
+            count as i64
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| DomainResponse {
+            fqdn: rec.fqdn,
+            canonical: rec.canonical,
+            score: [rec.score_true, rec.score_false],
+            last_updated: rec.last_updated,
+            ruegen: None,
+        })
+        .collect())
+    }
+
+    #[tracing::instrument(skip(hashes))]
+    /// count the domains matching each of multiple fqdn hash prefixes with a single query, stops counting at
+    /// `limit`. The counts are returned in the order of `hashes`
+    pub async fn count_by_hashes(
+        pool: &DbPool,
+        hashes: &[String],
+        limit: u32,
+    ) -> Result<Vec<i64>, APIError> {
+        let hashes: Vec<String> = hashes.iter().map(|hash| trim(hash)).collect();
+
+        Ok(sqlx::query!(
+            r#"select (
+                select count(*) from (
+                    select 1 from domains where fqdn_hash like concat(p.prefix, '%') limit $2
+                ) matching
+            ) as "count!"
+            from unnest($1::text[]) with ordinality as p(prefix, position)
+            order by p.position"#,
+            &hashes,
+            limit as i64
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| rec.count)
+        .collect())
+    }
+
+    #[tracing::instrument(skip(hashes))]
+    /// get the domains matching any of multiple fqdn hash prefixes with a single query.
+    /// Pagination is applied to each prefix, domains are returned with the prefix they matched
+    pub async fn by_hashes(
+        pool: &DbPool,
+        hashes: &[String],
+        per_page: u32,
+        page: u32,
+    ) -> Result<Vec<(String, DomainResponse)>, APIError> {
+        let hashes: Vec<String> = hashes.iter().map(|hash| trim(hash)).collect();
+
+        Ok(sqlx::query!(
+            r#"select p.prefix as "prefix!", d.fqdn, coalesce(c.last_updated, d.last_updated) as "last_updated!",
+            c.fqdn as "canonical?", coalesce(s.score_true, 0) as "score_true!", coalesce(s.score_false, 0) as "score_false!"
+        from unnest($1::text[]) p(prefix)
+        cross join lateral (
+            select * from domains where fqdn_hash like concat(p.prefix, '%')
+            order by fqdn_hash
+            limit $2
+            offset $3
+        ) d
+        left join domain_aliases a on a.alias_id = d.id
+        left join domains c on c.id = a.canonical_id
+        left join domain_scores s on s.domain_id = coalesce(a.canonical_id, d.id)
+            and s.run_id = (select max(id) from score_runs where state = 'finished')
+        order by p.prefix, d.fqdn_hash"#,
+            &hashes,
+            per_page as i64,
+            per_page as i64 * page as i64
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|rec| {
+            (
+                rec.prefix,
+                DomainResponse {
+                    fqdn: rec.fqdn,
+                    canonical: rec.canonical,
+                    score: [rec.score_true, rec.score_false],
+                    last_updated: rec.last_updated,
+                    ruegen: None,
+                },
+            )
+        })
+        .collect())
+    }
+
+    #[tracing::instrument]
+    /// get the domains matching a fqdn hash prefix.
+    /// Starts after `cursor` if given, otherwise at `page`. Returns the cursor of the next page if there is one
+    pub async fn by_hash(
+        pool: &DbPool,
+        hash: &String,
+        per_page: u32,
+        page: u32,
+        cursor: Option<&Cursor>,
+    ) -> Result<(Vec<DomainResponse>, Option<Cursor>), APIError> {
+        // aliases are answered with the information of their canonical domain
+        // scores are taken from the latest finished scoring run
+        let mut domains = sqlx::query!(
+            r#"select d.id, d.fqdn_hash, d.fqdn, coalesce(c.last_updated, d.last_updated) as "last_updated!", c.fqdn as "canonical?",
+            coalesce(s.score_true, 0) as "score_true!", coalesce(s.score_false, 0) as "score_false!"
+        from domains d
+        left join domain_aliases a on a.alias_id = d.id
+        left join domains c on c.id = a.canonical_id
+        left join domain_scores s on s.domain_id = coalesce(a.canonical_id, d.id)
+            and s.run_id = (select max(id) from score_runs where state = 'finished')
+        where d.fqdn_hash like concat($1::text, '%')
+            and ($2::text is null or (d.fqdn_hash, d.id) > ($2::text, $3::int))
+        order by d.fqdn_hash, d.id
+        limit $4
+        offset $5"#,
+            trim(hash),
+            cursor.map(|cursor| cursor.fqdn_hash.as_str()),
+            cursor.map_or(0, |cursor| cursor.id),
+            per_page as i64 + 1,
+            offset(per_page, page, cursor)
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let next = next_cursor(&mut domains, per_page, |rec| Cursor {
+            fqdn_hash: rec.fqdn_hash.clone(),
+            id: rec.id,
+        });
+
+        Ok((
+            domains
+                .into_iter()
+                .map(|rec| DomainResponse {
+                    fqdn: rec.fqdn,
+                    canonical: rec.canonical,
+                    score: [rec.score_true, rec.score_false],
+                    last_updated: rec.last_updated,
+                    ruegen: None,
+                })
+                .collect(),
+            next,
+        ))
+    }
+}
+
+/// Offset of the deprecated page based pagination, cursors always start right after their position
+fn offset(per_page: u32, page: u32, cursor: Option<&Cursor>) -> i64 {
+    match cursor {
+        Some(_) => 0,
+        None => per_page as i64 * page as i64,
+    }
+}
+
+/// Truncate `records`, fetched with a limit of `per_page + 1`, to `per_page` and return the cursor
+/// of the last record if there are more records
+fn next_cursor<T>(
+    records: &mut Vec<T>,
+    per_page: u32,
+    cursor: impl Fn(&T) -> Cursor,
+) -> Option<Cursor> {
+    if records.len() > per_page as usize {
+        records.truncate(per_page as usize);
+        records.last().map(cursor)
+    } else {
+        None
+    }
+}
+
+/// Updates of a batch that aren't stale, in their order. Like consecutive single updates, an update older than the last
+/// update of its source is stale. The existing sources are locked in a stable order, so concurrent batches can't update
+/// them after they were checked.
+async fn fresh<'a>(
+    tx: &mut Transaction<'_, Postgres>,
+    records: &'a [UpdateRequest],
+) -> Result<Vec<&'a UpdateRequest>, APIError> {
+    let fqdns: Vec<String> = records.iter().map(|record| trim(&record.fqdn)).collect();
+    let mut current: HashMap<String, i64> = sqlx::query!(
+        r#"select fqdn, last_updated from domains where fqdn = any($1) order by id for update"#,
+        &fqdns
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|rec| (rec.fqdn, rec.last_updated))
+    .collect();
+
+    let mut fresh = Vec::with_capacity(records.len());
+    for (record, fqdn) in records.iter().zip(fqdns) {
+        let last_updated = current.entry(fqdn).or_insert(record.last_updated);
+        if *last_updated > record.last_updated {
+            continue;
+        }
+        *last_updated = record.last_updated;
+        fresh.push(record);
+    }
+
+    Ok(fresh)
+}
+
+/// Split `fqdn -> (network, last_updated)` into the columns of `domains` for multi-row inserts with `unnest`
+fn domain_columns(
+    domains: HashMap<String, (bool, i64)>,
+) -> (Vec<String>, Vec<String>, Vec<bool>, Vec<i64>) {
+    let mut columns = (
+        Vec::with_capacity(domains.len()),
+        Vec::with_capacity(domains.len()),
+        Vec::with_capacity(domains.len()),
+        Vec::with_capacity(domains.len()),
+    );
+
+    for (fqdn, (network, last_updated)) in domains {
+        columns.1.push(SimpleDomain::hash(fqdn.as_bytes()));
+        columns.0.push(fqdn);
+        columns.2.push(network);
+        columns.3.push(last_updated);
+    }
+
+    columns
+}
+
+/// Append a row in the text format of `COPY`, escaping the delimiters within the columns
+pub fn copy_row(buffer: &mut String, columns: &[&str]) {
+    for (index, column) in columns.iter().enumerate() {
+        if index > 0 {
+            buffer.push('\t');
+        }
+        for c in column.chars() {
+            match c {
+                '\\' => buffer.push_str("\\\\"),
+                '\t' => buffer.push_str("\\t"),
+                '\n' => buffer.push_str("\\n"),
+                '\r' => buffer.push_str("\\r"),
+                c => buffer.push(c),
+            }
+        }
     }
+    buffer.push('\n');
 }
@@ -32,3 +32,29 @@ pub async fn init_pool() -> DbPool {
 pub fn trim_zero(s: &str) -> String {
     s.trim_matches('\0').to_owned()
 }
+
+/// Position in a listing ordered by `(fqdn_hash, id)`, used for keyset pagination
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub fqdn_hash: String,
+    pub id: i32,
+}
+
+impl Cursor {
+    /// Opaque representation handed out to clients
+    pub fn encode(&self) -> String {
+        format!("{}{:08x}", self.fqdn_hash, self.id as u32)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Cursor> {
+        if cursor.len() <= 8 || !cursor.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let (fqdn_hash, id) = cursor.split_at(cursor.len() - 8);
+        Some(Cursor {
+            fqdn_hash: fqdn_hash.to_owned(),
+            id: u32::from_str_radix(id, 16).ok()? as i32,
+        })
+    }
+}
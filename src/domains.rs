@@ -1,4 +1,10 @@
 pub mod graph;
+pub mod ingest;
+pub mod metrics;
+pub mod padding;
+pub mod prefixes;
+pub mod quarantine;
+pub mod queue;
 pub mod requests;
 pub mod responses;
 pub mod routes;
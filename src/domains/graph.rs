@@ -2,6 +2,7 @@ use self::vertex::DomainVertex;
 
 pub mod acg;
 pub mod awg;
+pub mod csr;
 pub mod vertex;
 
 #[derive(Debug)]
@@ -1,6 +1,11 @@
-use super::{vertex::DomainVertex, Link};
+use super::{
+    vertex::{DomainVertex, Vertex},
+    ColoredLink, Link,
+};
 use crate::domains::graph::GraphError;
 use dashmap::DashMap;
+use std::collections::HashMap;
+use std::mem::size_of;
 use std::sync::Arc;
 
 // Acyclic counter graph
@@ -15,6 +20,25 @@ impl ACG {
         }
     }
 
+    /// Build the graph from `(source_id, target_id, count)` triples as stored in `domain_link`.
+    /// Counts of repeated links are summed up.
+    pub fn from_edges(edges: &[(i32, i32, u32)]) -> Self {
+        let acg = Self::new();
+
+        for (source, target, count) in edges {
+            let target = DomainVertex::Simple(Vertex { id: *target });
+            acg.edges.entry(target.clone()).or_default();
+
+            let mut links = acg
+                .edges
+                .entry(DomainVertex::Simple(Vertex { id: *source }))
+                .or_default();
+            Self::add_count(links.value_mut(), &target, *count);
+        }
+
+        acg
+    }
+
     pub fn add_domain(&mut self, domain: DomainVertex) -> Result<(), GraphError> {
         match self.edges.insert(domain.clone(), vec![]) {
             Some(links) => {
@@ -79,41 +103,194 @@ impl ACG {
         }
     }
 
-    pub fn _decycle(
-        domain: DomainVertex,
-        path: Vec<DomainVertex>,
-        edges: Arc<DashMap<DomainVertex, Vec<Link>>>,
-        marked: Arc<DashMap<DomainVertex, bool>>,
-    ) {
-        let links = edges.get_mut(&domain).unwrap().value_mut();
-
-        for (index, link) in links.into_iter().enumerate() {
-            match path.binary_search(&link.target) {
-                Ok(_) => {
-                    links.remove(index);
+    /// Merge an alias (e.g. `www.spiegel.de` for `spiegel.de`) into its canonical domain.
+    /// The links of the alias are added to the canonical domain and all links to the alias are
+    /// redirected to it, so the trust isn't split between both vertices.
+    pub fn merge_alias(
+        &mut self,
+        alias: &DomainVertex,
+        canonical: &DomainVertex,
+    ) -> Result<(), GraphError> {
+        if !self.edges.contains_key(canonical) {
+            return Err(GraphError::DomainNotFound);
+        }
+
+        let alias_links = self.remove_domain(alias)?;
+
+        for mut entry in self.edges.iter_mut() {
+            let is_canonical = entry.key() == canonical;
+            let links = entry.value_mut();
+
+            if let Ok(index) = links.binary_search_by_key(alias, |link| link.target.clone()) {
+                let link = links.remove(index);
+
+                if !is_canonical {
+                    Self::add_count(links, canonical, link.count);
                 }
-                Err(_) => match marked.get(&link.target) {
-                    Some(node_marked) => {
-                        if !node_marked.value() {
-                            Self::_decycle(
-                                link.target,
-                                [path, vec![domain]].concat(),
-                                edges,
-                                marked.clone(),
-                            )
-                        }
+            }
+
+            if is_canonical {
+                for link in &alias_links {
+                    if &link.target != canonical {
+                        Self::add_count(links, &link.target, link.count);
                     }
-                    None => (),
-                },
-            };
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_count(links: &mut Vec<Link>, target: &DomainVertex, count: u32) {
+        match links.binary_search_by_key(target, |link| link.target.clone()) {
+            Ok(index) => links[index].count += count,
+            Err(index) => links.insert(index, Link::new(target.clone(), count)),
         }
     }
 
-    pub fn decycle(&mut self, root: Vec<DomainVertex>) {
-        let marked_map = Arc::new(DashMap::with_capacity(self.edges.capacity() - 1));
+    /// Propagate the root value through the graph with the tri-color marking described in graph.md.
+    ///
+    /// Every vertex starts out white (unmarked). A white vertex is marked gray once all of its
+    /// referrers are marked, which happens layer by layer (Kahn's algorithm). Marking a vertex
+    /// colors its outgoing links and adds its score times the weight of each link to the target.
+    /// A vertex is black once it and all of its in- and outgoing links are marked.
+    ///
+    /// Vertices unreachable from the roots are marked like every other vertex, they just pass on a
+    /// score of `0.0`. The graph has to be decycled beforehand, vertices on a remaining cycle stay
+    /// white and don't pass on their score. See [`CSRGraph::propagate`] for the read-only
+    /// counterpart used by scoring runs.
+    ///
+    /// [`CSRGraph::propagate`]: super::csr::CSRGraph::propagate
+    pub fn propagate(&self, roots: &[DomainVertex], root_value: f32) -> HashMap<i32, f32> {
+        let mut vertices: HashMap<i32, DomainVertex> = HashMap::with_capacity(self.edges.len());
+        let mut links: HashMap<i32, Vec<ColoredLink>> = HashMap::with_capacity(self.edges.len());
+        // count of uncolored links to each vertex
+        let mut referrers: HashMap<i32, u32> = HashMap::with_capacity(self.edges.len());
+
+        for entry in self.edges.iter() {
+            let id = entry.key().id();
+            vertices.insert(id, entry.key().clone().ensure_colorable());
+            referrers.entry(id).or_insert(0);
+
+            for link in entry.value() {
+                vertices
+                    .entry(link.target.id())
+                    .or_insert_with(|| link.target.clone().ensure_colorable());
+                *referrers.entry(link.target.id()).or_insert(0) += 1;
+            }
+            links.insert(
+                id,
+                entry
+                    .value()
+                    .iter()
+                    .cloned()
+                    .map(ColoredLink::from)
+                    .collect(),
+            );
+        }
+
+        let mut scores: HashMap<i32, f32> = vertices.keys().map(|id| (*id, 0.0)).collect();
+        for root in roots {
+            scores.insert(root.id(), root_value);
+        }
 
-        for domain in root {
-            Self::_decycle(domain, vec![], self.edges, marked_map.clone());
+        let mut layer: Vec<i32> = referrers
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        while !layer.is_empty() {
+            let mut next = vec![];
+
+            for id in layer {
+                if let Some(vertex) = vertices.remove(&id) {
+                    vertices.insert(id, vertex.mark());
+                }
+
+                let outgoing = match links.get_mut(&id) {
+                    Some(outgoing) => outgoing,
+                    None => continue,
+                };
+                let total: u32 = outgoing.iter().map(|colored| colored.link.count).sum();
+                let score = scores[&id];
+
+                for colored in outgoing.iter_mut() {
+                    colored.color = true;
+
+                    let target = colored.link.target.id();
+                    *scores.get_mut(&target).unwrap() +=
+                        score * colored.link.count as f32 / total as f32;
+
+                    let count = referrers.get_mut(&target).unwrap();
+                    *count -= 1;
+                    if *count == 0 && !vertices[&target].is_colored() {
+                        next.push(target);
+                    }
+                }
+            }
+
+            layer = next;
+        }
+
+        scores
+    }
+
+    /// Approximate heap memory used by the graph in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.edges
+            .iter()
+            .map(|entry| {
+                size_of::<DomainVertex>()
+                    + size_of::<Vec<Link>>()
+                    + entry.value().capacity() * size_of::<Link>()
+            })
+            .sum()
+    }
+
+    /// Remove all links closing a cycle with a depth first search from the roots.
+    /// A link to a vertex on the current path is removed, so links pointing away from the roots
+    /// are kept. Vertices unreachable from the roots are searched afterwards to also remove their
+    /// cycles.
+    pub fn decycle(&mut self, roots: Vec<DomainVertex>) {
+        // false: vertex is on the current path, true: all descendants of the vertex were searched
+        let mut marked: HashMap<i32, bool> = HashMap::with_capacity(self.edges.len());
+        let domains: Vec<DomainVertex> =
+            self.edges.iter().map(|entry| entry.key().clone()).collect();
+
+        for domain in roots.into_iter().chain(domains) {
+            if marked.contains_key(&domain.id()) {
+                continue;
+            }
+
+            marked.insert(domain.id(), false);
+            let mut path = vec![(domain, 0)];
+
+            while let Some((domain, index)) = path.pop() {
+                let target = self
+                    .edges
+                    .get(&domain)
+                    .and_then(|links| links.get(index).map(|link| link.target.clone()));
+
+                match target {
+                    None => {
+                        marked.insert(domain.id(), true);
+                    }
+                    Some(target) => match marked.get(&target.id()) {
+                        Some(false) => {
+                            // the link closes a cycle, the next link moves to the same index
+                            self.edges.get_mut(&domain).unwrap().remove(index);
+                            path.push((domain, index));
+                        }
+                        Some(true) => path.push((domain, index + 1)),
+                        None => {
+                            marked.insert(target.id(), false);
+                            path.push((domain, index + 1));
+                            path.push((target, 0));
+                        }
+                    },
+                }
+            }
         }
     }
 }
@@ -0,0 +1,317 @@
+use super::acg::ACG;
+use std::collections::HashMap;
+use std::mem::{self, size_of};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Barrier, Mutex, RwLock};
+use std::thread;
+
+/// Layers smaller than this are processed by a single worker, splitting them isn't worth it
+const PARALLEL_LAYER_THRESHOLD: usize = 1024;
+
+/// Progress of a running propagation, shared with the thread that started it
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// count of visited nodes
+    pub processed: AtomicUsize,
+    /// count of all nodes in the graph
+    pub total: AtomicUsize,
+    /// set to stop the propagation before the next layer
+    pub cancelled: AtomicBool,
+}
+
+/// Compressed sparse row representation of the acyclic counter graph.
+///
+/// Nodes are addressed by dense `u32` indices which are mapped to `domains.id`. The outgoing
+/// edges of node `n` are stored in `targets[offsets[n]..offsets[n + 1]]` with their weight at the
+/// same position in `weights`. The representation is read-only and meant for scoring runs, use
+/// the [`ACG`] for mutations.
+pub struct CSRGraph {
+    /// `domains.id` of each dense node index
+    pub ids: Vec<i32>,
+    /// dense node index of each `domains.id`
+    pub index: HashMap<i32, u32>,
+    pub offsets: Vec<u32>,
+    pub targets: Vec<u32>,
+    /// weight of each edge ($e_l$ in graph.md), i.e. the link count proportional to all links of the source
+    pub weights: Vec<f32>,
+}
+
+impl CSRGraph {
+    /// Build the graph from `(source_id, target_id, count)` triples as stored in `domain_link`.
+    pub fn from_edges(edges: &[(i32, i32, u32)]) -> Self {
+        let mut index = HashMap::new();
+        let mut ids = vec![];
+
+        for (source, target, _) in edges {
+            for id in [source, target] {
+                index.entry(*id).or_insert_with(|| {
+                    ids.push(*id);
+                    (ids.len() - 1) as u32
+                });
+            }
+        }
+
+        let mut adjacency: Vec<Vec<(u32, u32)>> = vec![vec![]; ids.len()];
+        for (source, target, count) in edges {
+            adjacency[index[source] as usize].push((index[target], *count));
+        }
+
+        Self::from_adjacency(ids, index, adjacency)
+    }
+
+    fn from_adjacency(
+        ids: Vec<i32>,
+        index: HashMap<i32, u32>,
+        adjacency: Vec<Vec<(u32, u32)>>,
+    ) -> Self {
+        let edge_count = adjacency.iter().map(Vec::len).sum();
+        let mut offsets = Vec::with_capacity(ids.len() + 1);
+        let mut targets = Vec::with_capacity(edge_count);
+        let mut weights = Vec::with_capacity(edge_count);
+
+        offsets.push(0);
+        for mut links in adjacency {
+            links.sort_unstable();
+            let total: u32 = links.iter().map(|(_, count)| count).sum();
+
+            for (target, count) in links {
+                targets.push(target);
+                weights.push(count as f32 / total as f32);
+            }
+            offsets.push(targets.len() as u32);
+        }
+
+        Self {
+            ids,
+            index,
+            offsets,
+            targets,
+            weights,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Dense index range of the outgoing edges of `node`
+    pub fn edge_range(&self, node: u32) -> std::ops::Range<usize> {
+        self.offsets[node as usize] as usize..self.offsets[node as usize + 1] as usize
+    }
+
+    /// Count of referrers for each node
+    pub fn in_degrees(&self) -> Vec<u32> {
+        let mut degrees = vec![0; self.node_count()];
+        for target in &self.targets {
+            degrees[*target as usize] += 1;
+        }
+        degrees
+    }
+
+    /// Propagate the root value through the graph layer by layer.
+    /// A node is visited once all of its referrers have been visited (Kahn's algorithm), so the
+    /// graph has to be decycled beforehand. Nodes on a remaining cycle still receive the score of
+    /// their visited referrers but don't propagate it any further.
+    /// Returns the score of every dense node index.
+    pub fn propagate(&self, roots: &[i32], root_value: f32) -> Vec<f32> {
+        let mut scores = vec![0.0; self.node_count()];
+        for root in roots {
+            if let Some(node) = self.index.get(root) {
+                scores[*node as usize] = root_value;
+            }
+        }
+
+        let mut degrees = self.in_degrees();
+        let mut layer: Vec<u32> = (0..self.node_count() as u32)
+            .filter(|node| degrees[*node as usize] == 0)
+            .collect();
+
+        while !layer.is_empty() {
+            let mut next = vec![];
+
+            for node in layer {
+                let score = scores[node as usize];
+
+                for edge in self.edge_range(node) {
+                    let target = self.targets[edge] as usize;
+                    scores[target] += score * self.weights[edge];
+
+                    degrees[target] -= 1;
+                    if degrees[target] == 0 {
+                        next.push(target as u32);
+                    }
+                }
+            }
+
+            layer = next;
+        }
+
+        scores
+    }
+
+    /// Parallel variant of [`Self::propagate`].
+    /// Every layer is split into contiguous ranges which are processed by `workers` threads. The threads are
+    /// started once and step through the layers together, scores and referrer counts are updated atomically. Visited nodes are added to
+    /// `progress.processed`. Returns `None` if the propagation was cancelled through `progress`.
+    pub fn propagate_parallel(
+        &self,
+        roots: &[i32],
+        root_value: f32,
+        workers: usize,
+        progress: &Progress,
+    ) -> Option<Vec<f32>> {
+        let scores: Vec<AtomicU32> = (0..self.node_count())
+            .map(|_| AtomicU32::new(0.0_f32.to_bits()))
+            .collect();
+        for root in roots {
+            if let Some(node) = self.index.get(root) {
+                scores[*node as usize].store(root_value.to_bits(), Ordering::Relaxed);
+            }
+        }
+
+        let degrees: Vec<AtomicU32> = self.in_degrees().into_iter().map(AtomicU32::new).collect();
+        let layer: Vec<u32> = (0..self.node_count() as u32)
+            .filter(|node| degrees[*node as usize].load(Ordering::Relaxed) == 0)
+            .collect();
+
+        let visit = |nodes: &[u32]| {
+            let mut next = vec![];
+
+            for node in nodes {
+                let score = f32::from_bits(scores[*node as usize].load(Ordering::Relaxed));
+
+                for edge in self.edge_range(*node) {
+                    let target = self.targets[edge] as usize;
+                    add_f32(&scores[target], score * self.weights[edge]);
+
+                    // the last referrer to be visited queues the target for the next layer
+                    if degrees[target].fetch_sub(1, Ordering::Relaxed) == 1 {
+                        next.push(target as u32);
+                    }
+                }
+            }
+
+            next
+        };
+
+        let workers = workers.max(1);
+        let finished =
+            |layer: &[u32]| layer.is_empty() || progress.cancelled.load(Ordering::Relaxed);
+        let stop = AtomicBool::new(finished(&layer));
+        let layer = RwLock::new(layer);
+        let next: Vec<Mutex<Vec<u32>>> = (0..workers).map(|_| Mutex::new(vec![])).collect();
+        let barrier = Barrier::new(workers);
+
+        // the barrier orders all updates of a layer before the next one, the first worker queues the next layer
+        let step = |worker: usize| loop {
+            barrier.wait();
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let nodes = layer.read().unwrap();
+            let range = chunk(nodes.len(), worker, workers);
+            *next[worker].lock().unwrap() = visit(&nodes[range]);
+            drop(nodes);
+
+            barrier.wait();
+            if worker == 0 {
+                let mut nodes = layer.write().unwrap();
+                progress.processed.fetch_add(nodes.len(), Ordering::Relaxed);
+                *nodes = next
+                    .iter()
+                    .flat_map(|next| mem::take(&mut *next.lock().unwrap()))
+                    .collect();
+                stop.store(finished(&nodes), Ordering::Relaxed);
+            }
+        };
+
+        thread::scope(|scope| {
+            let step = &step;
+            for worker in 1..workers {
+                scope.spawn(move || step(worker));
+            }
+            step(0);
+        });
+
+        // the propagation only stops early if it was cancelled
+        if !layer.into_inner().unwrap().is_empty() {
+            return None;
+        }
+
+        Some(
+            scores
+                .into_iter()
+                .map(|score| f32::from_bits(score.into_inner()))
+                .collect(),
+        )
+    }
+
+    /// Approximate heap memory used by the graph in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.ids.capacity() * size_of::<i32>()
+            + self.index.capacity() * (size_of::<i32>() + size_of::<u32>())
+            + self.offsets.capacity() * size_of::<u32>()
+            + self.targets.capacity() * size_of::<u32>()
+            + self.weights.capacity() * size_of::<f32>()
+    }
+}
+
+impl From<&ACG> for CSRGraph {
+    fn from(acg: &ACG) -> Self {
+        let mut index = HashMap::with_capacity(acg.edges.len());
+        let mut ids = Vec::with_capacity(acg.edges.len());
+
+        for entry in acg.edges.iter() {
+            let id = entry.key().id();
+            index.insert(id, ids.len() as u32);
+            ids.push(id);
+        }
+
+        let mut adjacency: Vec<Vec<(u32, u32)>> = vec![vec![]; ids.len()];
+        for entry in acg.edges.iter() {
+            let source = index[&entry.key().id()] as usize;
+
+            for link in entry.value() {
+                let target = *index.entry(link.target.id()).or_insert_with(|| {
+                    ids.push(link.target.id());
+                    adjacency.push(vec![]);
+                    (ids.len() - 1) as u32
+                });
+                adjacency[source].push((target, link.count));
+            }
+        }
+
+        Self::from_adjacency(ids, index, adjacency)
+    }
+}
+
+/// Range of a layer of `len` nodes processed by `worker`. Small layers are processed by the first worker alone
+fn chunk(len: usize, worker: usize, workers: usize) -> Range<usize> {
+    if len < PARALLEL_LAYER_THRESHOLD {
+        return if worker == 0 { 0..len } else { 0..0 };
+    }
+
+    let size = len.div_ceil(workers);
+    let start = (worker * size).min(len);
+    start..(start + size).min(len)
+}
+
+/// Atomically add `value` to a `f32` stored as bits in `cell`
+fn add_f32(cell: &AtomicU32, value: f32) {
+    let mut current = cell.load(Ordering::Relaxed);
+
+    loop {
+        let updated = (f32::from_bits(current) + value).to_bits();
+
+        match cell.compare_exchange_weak(current, updated, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(actual) => current = actual,
+        }
+    }
+}
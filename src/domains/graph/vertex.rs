@@ -5,6 +5,14 @@ pub enum DomainVertex {
 }
 
 impl DomainVertex {
+    /// `domains.id` of the vertex
+    pub fn id(&self) -> i32 {
+        match self {
+            Self::Simple(vertex) => vertex.id,
+            Self::Colorable(vertex) => vertex.id,
+        }
+    }
+
     pub fn is_colored(&self) -> bool {
         match self {
             Self::Simple(_) => false,
@@ -15,7 +23,10 @@ impl DomainVertex {
     pub fn mark(self) -> Self {
         match self {
             Self::Simple(vertex) => Self::Colorable(ColorableVertex::marked(vertex)),
-            Self::Colorable(vertex) => Self::Colorable(vertex),
+            Self::Colorable(vertex) => Self::Colorable(ColorableVertex {
+                color: true,
+                ..vertex
+            }),
         }
     }
 
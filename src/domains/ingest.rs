@@ -0,0 +1,147 @@
+use super::quarantine;
+use super::requests::UpdateRequest;
+use super::responses::{IngestResponse, RejectedLine};
+use super::util::parse_update;
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::core::util::timestamp;
+use crate::db::models::SimpleDomain;
+use crate::db::util::DbPool;
+
+/// Bulk ingestion of line-delimited updates, e.g. NDJSON streamed by scrapers.
+///
+/// Each line holds a single [`UpdateRequest`]. Valid records are collected into batches of
+/// `ingest.batch_records` which are written with multi-row inserts, see [`SimpleDomain::ingest`].
+/// Invalid lines are reported with their line number and don't affect the rest of the payload,
+/// they are quarantined with the batch written next, see [`quarantine`].
+pub struct Ingestion<'a> {
+    pool: &'a DbPool,
+    /// scraper the links are attributed to
+    scraper_id: Option<i32>,
+    /// time the payload was received, the links are reported at
+    received_at: i64,
+    /// valid records that weren't written yet with their line number
+    batch: Vec<(usize, UpdateRequest)>,
+    /// rejected lines that weren't quarantined yet
+    rejected: Vec<(String, APIError)>,
+    summary: IngestResponse,
+}
+
+impl<'a> Ingestion<'a> {
+    pub fn new(pool: &'a DbPool, scraper_id: Option<i32>) -> Self {
+        Self {
+            pool,
+            scraper_id,
+            received_at: timestamp(),
+            batch: Vec::with_capacity(CONFIG.ingest.batch_records),
+            rejected: vec![],
+            summary: IngestResponse::default(),
+        }
+    }
+
+    /// Process the next line of the payload, writing the batch once it or the rejected lines are full
+    pub async fn line(&mut self, line: &[u8]) -> Result<(), APIError> {
+        self.summary.lines += 1;
+        let number = self.summary.lines;
+
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        match parse_update(line) {
+            Ok(record) => self.batch.push((number, record)),
+            Err(e) => self.reject(number, String::from_utf8_lossy(line).into_owned(), e),
+        }
+
+        if self.batch.len() >= CONFIG.ingest.batch_records
+            || self.rejected.len() >= CONFIG.ingest.batch_records
+        {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the remaining records and return the summary of the ingestion
+    pub async fn finish(mut self) -> Result<IngestResponse, APIError> {
+        self.flush().await?;
+
+        Ok(self.summary)
+    }
+
+    /// Write the current batch and quarantine the rejected lines. Records that can't be written are
+    /// rejected, unless the database is unavailable, which fails the whole ingestion.
+    async fn flush(&mut self) -> Result<(), APIError> {
+        let written = self.write().await;
+
+        let rejected: Vec<(String, APIError)> = self.rejected.drain(..).collect();
+        quarantine::store(self.pool, &rejected, self.scraper_id, "ingest").await;
+
+        written
+    }
+
+    async fn write(&mut self) -> Result<(), APIError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        let (numbers, records): (Vec<usize>, Vec<UpdateRequest>) = self.batch.drain(..).unzip();
+        let failed = write(self.pool, &records, self.scraper_id, self.received_at).await?;
+
+        self.summary.accepted += records.len() - failed.len();
+        for (index, e) in failed {
+            let payload = serde_json::to_string(&records[index]).unwrap_or_default();
+            self.reject(numbers[index], payload, e);
+        }
+
+        Ok(())
+    }
+
+    fn reject(&mut self, line: usize, payload: String, error: APIError) {
+        self.summary.rejected.push(RejectedLine {
+            line,
+            loc: match error {
+                APIError::ValidationError(loc, _) => Some(loc),
+                _ => None,
+            },
+            msg: String::from(&error),
+        });
+        self.rejected.push((payload, error));
+    }
+}
+
+/// Write a batch of records, see [`SimpleDomain::ingest`]. If the batch can't be written, e.g. due
+/// to a single conflicting record, its records are written one by one so only the failing ones are
+/// left out. Returns the index of each record that couldn't be written with its error. Fails if the
+/// database is unavailable, records written up to then are written again by a retry.
+pub async fn write(
+    pool: &DbPool,
+    records: &[UpdateRequest],
+    scraper_id: Option<i32>,
+    received_at: i64,
+) -> Result<Vec<(usize, APIError)>, APIError> {
+    match SimpleDomain::ingest(pool, records, scraper_id, received_at).await {
+        Ok(()) => return Ok(vec![]),
+        Err(APIError::PoolError) => return Err(APIError::PoolError),
+        Err(e) if records.len() == 1 => return Ok(vec![(0, e)]),
+        Err(e) => tracing::warn!(
+            "Failed to ingest batch of {} records, retrying them one by one: {}",
+            records.len(),
+            String::from(e)
+        ),
+    }
+
+    let mut failed = vec![];
+    for (index, record) in records.iter().enumerate() {
+        match SimpleDomain::ingest(pool, std::slice::from_ref(record), scraper_id, received_at)
+            .await
+        {
+            Ok(()) => {}
+            Err(APIError::PoolError) => return Err(APIError::PoolError),
+            Err(e) => failed.push((index, e)),
+        }
+    }
+
+    Ok(failed)
+}
@@ -0,0 +1,29 @@
+use super::queue::IngestQueue;
+use crate::core::config::CONFIG;
+use actix_web::web::Data;
+use lazy_static::lazy_static;
+use opentelemetry::{global, metrics::Counter, KeyValue};
+
+lazy_static! {
+    static ref QUEUED_UPDATES: Counter<u64> = global::meter(&CONFIG.tracing.meter)
+        .u64_counter("ingest.queue.updates")
+        .with_description(
+            "Count of updates sent to the ingestion queue by their state, i.e. queued, rejected, written or failed"
+        )
+        .init();
+}
+
+/// Record `count` updates reaching `state`
+pub fn record_updates(state: &'static str, count: usize) {
+    QUEUED_UPDATES.add(count as u64, &[KeyValue::new("state", state)]);
+}
+
+/// Report the depth of the ingestion queue on every metrics collection
+pub fn observe_depth(queue: Data<IngestQueue>) {
+    global::meter(&CONFIG.tracing.meter)
+        .u64_value_observer("ingest.queue.depth", move |result| {
+            result.observe(queue.depth() as u64, &[]);
+        })
+        .with_description("Count of updates waiting in the ingestion queue")
+        .init();
+}
@@ -0,0 +1,191 @@
+use super::responses::{
+    AggregatedDomainResponse, BatchDomainResponse, DomainResponse, PrefixDomains,
+};
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::core::types::{APIResponse, Format, Negotiated};
+use crate::db::models::{Ruege, SimpleDomain};
+use crate::db::util::DbPool;
+use actix_web::{http::header, HttpRequest, HttpResponse, HttpResponseBuilder, Responder};
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+
+/// Responses that can be padded to a fixed size in bytes
+trait Padded: Serialize {
+    fn padding(&mut self) -> &mut Option<String>;
+}
+
+impl Padded for AggregatedDomainResponse {
+    fn padding(&mut self) -> &mut Option<String> {
+        &mut self.padding
+    }
+}
+
+impl Padded for BatchDomainResponse {
+    fn padding(&mut self) -> &mut Option<String> {
+        &mut self.padding
+    }
+}
+
+/// Build a fetch response for the matches of `lookups` prefixes.
+/// `next_cursor` is the cursor of the next page of the matches, decoys never affect it.
+///
+/// If padding is enabled the response always holds a fixed count of entries, filled up with
+/// decoys from random buckets, and/ or has a fixed size in bytes. Clients tell actual matches
+/// apart by comparing the full hash. Padded responses are never compressed, since the
+/// compression ratio would leak the content again. The size applies to the format negotiated
+/// with the client.
+/// Rügen are embedded after adding the decoys, so decoys carry them as well.
+pub async fn respond(
+    req: &HttpRequest,
+    pool: &DbPool,
+    mut domains: Vec<DomainResponse>,
+    next_cursor: Option<String>,
+    lookups: usize,
+    with_ruegen: bool,
+) -> APIResponse {
+    let config = &CONFIG.padding;
+    let padded = config.entries.is_some() || config.bytes.is_some();
+
+    if !padded && domains.is_empty() {
+        return Err(APIError::NotFoundError);
+    }
+
+    if let Some(entries) = config.entries {
+        add_decoys(pool, &mut domains, entries * lookups).await?;
+    }
+    if with_ruegen {
+        embed_ruegen(pool, domains.iter_mut().collect()).await?;
+    }
+
+    let mut response = AggregatedDomainResponse {
+        domains,
+        next_cursor,
+        padding: None,
+    };
+    if let Some(bytes) = config.bytes {
+        pad_bytes(Format::negotiate(req), &mut response, bytes * lookups)?;
+    }
+
+    Ok(Negotiated::new(build(padded), response).respond_to(req))
+}
+
+/// Build a batch response, see [`respond`]. Each group of the batch is padded on its own, so the
+/// matches of a single prefix can't be told apart either.
+pub async fn respond_batch(
+    req: &HttpRequest,
+    pool: &DbPool,
+    mut results: Vec<PrefixDomains>,
+    with_ruegen: bool,
+) -> APIResponse {
+    let config = &CONFIG.padding;
+    let padded = config.entries.is_some() || config.bytes.is_some();
+
+    if let Some(entries) = config.entries {
+        for group in &mut results {
+            add_decoys(pool, &mut group.domains, entries).await?;
+        }
+    }
+    // the rügen of all groups are fetched at once
+    if with_ruegen {
+        embed_ruegen(
+            pool,
+            results
+                .iter_mut()
+                .flat_map(|group| group.domains.iter_mut())
+                .collect(),
+        )
+        .await?;
+    }
+
+    let lookups = results.len();
+    let mut response = BatchDomainResponse {
+        results,
+        padding: None,
+    };
+    if let Some(bytes) = config.bytes {
+        pad_bytes(Format::negotiate(req), &mut response, bytes * lookups)?;
+    }
+
+    Ok(Negotiated::new(build(padded), response).respond_to(req))
+}
+
+fn build(padded: bool) -> HttpResponseBuilder {
+    let mut builder = HttpResponse::Ok();
+    if padded {
+        builder.insert_header((header::CONTENT_ENCODING, "identity"));
+    }
+    builder
+}
+
+/// Embed the rügen linked to each domain
+async fn embed_ruegen(pool: &DbPool, domains: Vec<&mut DomainResponse>) -> Result<(), APIError> {
+    let mut fqdns: Vec<String> = domains.iter().map(|domain| domain.fqdn.clone()).collect();
+    // groups of a batch may share domains
+    fqdns.sort();
+    fqdns.dedup();
+    let ruegen = Ruege::by_fqdns(pool, &fqdns).await?;
+
+    for domain in domains {
+        domain.ruegen = Some(ruegen.get(&domain.fqdn).cloned().unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Fill up `domains` with decoys and shuffle them, so matches can't be told apart by their position
+async fn add_decoys(
+    pool: &DbPool,
+    domains: &mut Vec<DomainResponse>,
+    entries: usize,
+) -> Result<(), APIError> {
+    if domains.len() < entries {
+        let start = format!("{:016x}", rand::thread_rng().gen::<u64>());
+        let missing = entries - domains.len();
+        let decoys: Vec<DomainResponse> =
+            SimpleDomain::decoys(pool, &start, (missing + domains.len()) as u32)
+                .await?
+                .into_iter()
+                .filter(|decoy| domains.iter().all(|domain| domain.fqdn != decoy.fqdn))
+                .collect();
+
+        // small instances may not have enough domains, decoys are repeated then
+        for index in 0..missing {
+            match decoys.get(index % decoys.len().max(1)) {
+                Some(decoy) => domains.push(decoy.clone()),
+                None => break,
+            }
+        }
+    }
+
+    domains.shuffle(&mut rand::thread_rng());
+    Ok(())
+}
+
+/// Pad the serialized response to `bytes`. Responses already exceeding the size aren't padded
+fn pad_bytes<T: Padded>(format: Format, response: &mut T, bytes: usize) -> Result<(), APIError> {
+    *response.padding() = Some(String::new());
+    let length = format.serialize(response)?.len();
+
+    if length > bytes {
+        *response.padding() = None;
+        tracing::warn!(
+            "Fetch response of {} bytes exceeds the configured padding of {} bytes",
+            length,
+            bytes
+        );
+        return Ok(());
+    }
+
+    // binary formats prefix the filler with its length, which may grow with the filler
+    let mut filler = bytes - length;
+    loop {
+        *response.padding() = Some(" ".repeat(filler));
+        let length = format.serialize(response)?.len();
+
+        if length <= bytes || filler == 0 {
+            return Ok(());
+        }
+        filler -= (length - bytes).min(filler);
+    }
+}
@@ -0,0 +1,135 @@
+use super::responses::{PrefixDeltaResponse, PrefixEntry, PrefixListResponse};
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::db::models::{DomainScore, PrefixList};
+use crate::db::util::DbPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Latest published prefix list and the deltas requested for it
+struct CachedList {
+    list: Arc<PrefixListResponse>,
+    deltas: HashMap<i32, Arc<PrefixDeltaResponse>>,
+}
+
+/// Cache of the latest published prefix list.
+/// Loading the list touches every scored domain, so it is only reloaded once a new list was published.
+#[derive(Default)]
+pub struct PrefixListCache {
+    cached: RwLock<Option<CachedList>>,
+}
+
+impl PrefixListCache {
+    /// Get the latest published prefix list, `None` if no list was published yet
+    pub async fn latest(&self, pool: &DbPool) -> Result<Option<Arc<PrefixListResponse>>, APIError> {
+        let published = match PrefixList::latest(pool).await? {
+            Some(published) => published,
+            None => return Ok(None),
+        };
+
+        if let Some(cached) = self.cached.read().unwrap().as_ref() {
+            if cached.list.version == published.run_id {
+                return Ok(Some(cached.list.clone()));
+            }
+        }
+
+        let list = Arc::new(PrefixListResponse {
+            version: published.run_id,
+            published_at: published.published_at,
+            prefix_length: published.prefix_length as usize,
+            entries: published.entries(pool).await?,
+            bands: published.bands,
+        });
+        *self.cached.write().unwrap() = Some(CachedList {
+            list: list.clone(),
+            deltas: HashMap::new(),
+        });
+
+        Ok(Some(list))
+    }
+
+    /// Get the changes between the list of version `since` and `list`.
+    /// `None` if the client has to download the full list, because `since` isn't retained anymore
+    /// or was published with a different prefix length or score bands.
+    pub async fn delta(
+        &self,
+        pool: &DbPool,
+        list: &PrefixListResponse,
+        since: i32,
+    ) -> Result<Option<Arc<PrefixDeltaResponse>>, APIError> {
+        if let Some(cached) = self.cached.read().unwrap().as_ref() {
+            if cached.list.version == list.version {
+                if let Some(delta) = cached.deltas.get(&since) {
+                    return Ok(Some(delta.clone()));
+                }
+            }
+        }
+
+        match PrefixList::get(pool, since).await? {
+            Some(previous)
+                if previous.prefix_length as usize == list.prefix_length
+                    && previous.bands == list.bands => {}
+            _ => return Ok(None),
+        }
+
+        let mut delta = PrefixDeltaResponse {
+            since,
+            version: list.version,
+            added: vec![],
+            changed: vec![],
+            removed: vec![],
+        };
+        for change in PrefixList::changes(pool, since, list.version).await? {
+            match change.bands {
+                Some(bands) if change.added => delta.added.push(PrefixEntry(change.prefix, bands)),
+                Some(bands) => delta.changed.push(PrefixEntry(change.prefix, bands)),
+                None => delta.removed.push(change.prefix),
+            }
+        }
+
+        let delta = Arc::new(delta);
+        if let Some(cached) = self.cached.write().unwrap().as_mut() {
+            if cached.list.version == list.version {
+                cached.deltas.insert(since, delta.clone());
+            }
+        }
+
+        Ok(Some(delta))
+    }
+}
+
+/// Publish the prefix list of a finished scoring run
+pub async fn publish(pool: &DbPool, run_id: i32) -> Result<PrefixList, APIError> {
+    let config = &CONFIG.prefix_list;
+
+    let entries: Vec<PrefixEntry> = DomainScore::by_prefix(pool, run_id, config.prefix_length)
+        .await?
+        .into_iter()
+        .map(|prefix| {
+            PrefixEntry(
+                prefix.prefix,
+                [band(prefix.score[0]), band(prefix.score[1])],
+            )
+        })
+        .collect();
+
+    PrefixList::create(
+        pool,
+        run_id,
+        config.prefix_length as i32,
+        &config.bands,
+        &entries,
+        config.delta_versions as i64,
+    )
+    .await
+}
+
+/// Score band of `score`, i.e. the count of band bounds it reaches
+pub fn band(score: f32) -> u8 {
+    CONFIG
+        .prefix_list
+        .bands
+        .iter()
+        .filter(|bound| score >= **bound)
+        .count() as u8
+}
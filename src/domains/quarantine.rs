@@ -0,0 +1,105 @@
+use super::requests::UpdateRequest;
+use super::util::parse_update;
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::db::models::QuarantinedPayload;
+use crate::db::util::DbPool;
+
+/// Whether a payload rejected for `error` is quarantined. Other errors, e.g. an unavailable
+/// database, aren't caused by the payload itself
+pub fn quarantines(error: &APIError) -> bool {
+    matches!(
+        error,
+        APIError::ValidationError(..) | APIError::IntegrityError
+    )
+}
+
+/// Keep rejected payloads with the reason they were rejected for, so scraper bugs can be debugged
+/// and the payloads replayed once fixed. `source` names the endpoint or worker that rejected them.
+/// Payloads are kept for `quarantine.retention` seconds and up to `quarantine.max_payloads` per
+/// scraper, anonymous payloads only with `quarantine.anonymous`. Failing to quarantine is only
+/// logged, the rejection is reported to the scraper either way.
+pub async fn store(
+    pool: &DbPool,
+    rejected: &[(String, APIError)],
+    scraper_id: Option<i32>,
+    source: &str,
+) {
+    let config = &CONFIG.quarantine;
+    if scraper_id.is_none() && !config.anonymous {
+        return;
+    }
+
+    let rejected: Vec<&(String, APIError)> = rejected
+        .iter()
+        .filter(|(_, error)| quarantines(error))
+        .collect();
+    if rejected.is_empty() {
+        return;
+    }
+
+    let payloads: Vec<String> = rejected
+        .iter()
+        .map(|(payload, _)| payload.clone())
+        .collect();
+    let reasons: Vec<String> = rejected.iter().map(|(_, error)| error.into()).collect();
+    let locs: Vec<String> = rejected
+        .iter()
+        .map(|(_, error)| loc(error).unwrap_or_default().to_owned())
+        .collect();
+
+    match QuarantinedPayload::create(pool, &payloads, &reasons, &locs, scraper_id, source, config)
+        .await
+    {
+        Ok(0) => tracing::debug!(
+            "Quarantine of scraper {:?} is full, dropping {} rejected payloads",
+            scraper_id,
+            payloads.len()
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::warn!(
+            "Failed to quarantine {} rejected payloads: {}",
+            payloads.len(),
+            String::from(e)
+        ),
+    }
+}
+
+/// Quarantine updates that were already parsed, e.g. a batch that couldn't be written
+pub async fn store_updates(
+    pool: &DbPool,
+    updates: &[UpdateRequest],
+    error: &APIError,
+    scraper_id: Option<i32>,
+    source: &str,
+) {
+    let rejected: Vec<(String, APIError)> = updates
+        .iter()
+        .filter_map(|update| serde_json::to_string(update).ok())
+        .map(|payload| (payload, error.clone()))
+        .collect();
+
+    store(pool, &rejected, scraper_id, source).await
+}
+
+/// Validate a quarantined payload against the current rules. The stored reason is updated if the
+/// payload is still rejected
+pub async fn revalidate(
+    pool: &DbPool,
+    quarantined: &QuarantinedPayload,
+) -> Result<Result<UpdateRequest, APIError>, APIError> {
+    let parsed = parse_update(quarantined.payload.as_bytes());
+
+    if let Err(e) = &parsed {
+        QuarantinedPayload::update_reason(pool, quarantined.id, &String::from(e), loc(e)).await?;
+    }
+
+    Ok(parsed)
+}
+
+fn loc(error: &APIError) -> Option<&'static str> {
+    match error {
+        APIError::ValidationError([loc], _) => Some(*loc),
+        _ => None,
+    }
+}
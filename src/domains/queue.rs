@@ -0,0 +1,272 @@
+use super::ingest;
+use super::metrics;
+use super::quarantine;
+use super::requests::UpdateRequest;
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::core::util::timestamp;
+use crate::db::models::IngestQueueEntry;
+use crate::db::util::DbPool;
+use actix_web::web::Data;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Validated update waiting to be written to the graph
+#[derive(Debug)]
+pub struct QueuedUpdate {
+    pub update: UpdateRequest,
+    /// scraper the links are attributed to
+    pub scraper_id: Option<i32>,
+    /// time the update was received, the links are reported at
+    pub received_at: i64,
+}
+
+/// Bounded queue decoupling `/update` from writing to the graph.
+///
+/// Updates are written by background workers in batches with multi-row inserts, see
+/// [`SimpleDomain::ingest`], so a burst of scrapers doesn't exhaust the connection pool. Once
+/// `ingest.queue_capacity` updates are waiting further updates are rejected until the workers
+/// caught up. The queue is kept in-process by default and drained on shutdown, so only a crash loses
+/// queued updates. With `ingest.queue_durable` it is backed by the `ingest_queue` table instead,
+/// whose depth is refreshed by the workers and hence approximate.
+#[derive(Default)]
+pub struct IngestQueue {
+    /// updates of the in-process queue, always empty if the queue is durable
+    pending: Mutex<VecDeque<QueuedUpdate>>,
+    /// count of queued updates, including the batches currently written
+    depth: AtomicUsize,
+}
+
+impl IngestQueue {
+    /// `false` if updates are written synchronously
+    pub fn enabled() -> bool {
+        CONFIG.ingest.queue_capacity > 0
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    /// Queue a validated update. Fails with `503 Service Unavailable` if the queue is full
+    pub async fn push(
+        &self,
+        pool: &DbPool,
+        update: UpdateRequest,
+        scraper_id: Option<i32>,
+    ) -> Result<(), APIError> {
+        // the slot is reserved first, so concurrent requests can't exceed the capacity
+        if self.depth.fetch_add(1, Ordering::SeqCst) >= CONFIG.ingest.queue_capacity {
+            self.depth.fetch_sub(1, Ordering::SeqCst);
+            metrics::record_updates("rejected", 1);
+
+            return Err(APIError::UnavailableError(
+                "The ingestion queue is full, retry later".to_owned(),
+            ));
+        }
+
+        if CONFIG.ingest.queue_durable {
+            let pushed = match serde_json::to_string(&update) {
+                Ok(payload) => IngestQueueEntry::push(pool, &payload, scraper_id).await,
+                Err(e) => Err(APIError::InternalError(e.to_string())),
+            };
+            if let Err(e) = pushed {
+                self.depth.fetch_sub(1, Ordering::SeqCst);
+                return Err(e);
+            }
+        } else {
+            self.pending.lock().unwrap().push_back(QueuedUpdate {
+                update,
+                scraper_id,
+                received_at: timestamp(),
+            });
+        }
+
+        metrics::record_updates("queued", 1);
+        Ok(())
+    }
+
+    /// Start `ingest.queue_workers` background workers writing the queued updates
+    pub async fn spawn_workers(queue: Data<IngestQueue>, pool: DbPool) {
+        // updates left over from before a restart count towards the capacity
+        if CONFIG.ingest.queue_durable {
+            if let Err(e) = queue.refresh_depth(&pool).await {
+                tracing::warn!(
+                    "Failed to load the depth of the ingestion queue: {}",
+                    String::from(e)
+                );
+            }
+        }
+
+        for _ in 0..CONFIG.ingest.queue_workers {
+            actix_web::rt::spawn(Self::work(queue.clone(), pool.clone()));
+        }
+    }
+
+    /// Write the updates left in the in-process queue, e.g. once the server stopped accepting
+    /// requests on shutdown. Entries of the durable queue are kept for the next start.
+    pub async fn drain(&self, pool: &DbPool) {
+        if CONFIG.ingest.queue_durable {
+            return;
+        }
+        let idle = Duration::from_millis(CONFIG.ingest.queue_poll_interval);
+
+        // batches taken by the workers are still counted until they are written
+        while self.depth() > 0 {
+            match self.process(pool).await {
+                Ok(0) => actix_web::rt::time::sleep(idle).await,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to drain the ingestion queue, {} queued updates are lost: {}",
+                        self.depth(),
+                        String::from(e)
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn work(queue: Data<IngestQueue>, pool: DbPool) {
+        let idle = Duration::from_millis(CONFIG.ingest.queue_poll_interval);
+
+        loop {
+            match queue.process(&pool).await {
+                Ok(0) => actix_web::rt::time::sleep(idle).await,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to process the ingestion queue: {}", String::from(e));
+                    actix_web::rt::time::sleep(idle).await;
+                }
+            }
+        }
+    }
+
+    /// Write the next batch of queued updates. Returns the count of processed updates
+    async fn process(&self, pool: &DbPool) -> Result<usize, APIError> {
+        let (ids, batch) = self.pop(pool).await?;
+        if ids.is_empty() && batch.is_empty() {
+            return Ok(0);
+        }
+        let processed = batch.len();
+
+        // links are reported per written batch, so updates are grouped by their scraper and receive time
+        let mut groups: HashMap<(Option<i32>, i64), Vec<UpdateRequest>> = HashMap::new();
+        for queued in batch {
+            groups
+                .entry((queued.scraper_id, queued.received_at))
+                .or_default()
+                .push(queued.update);
+        }
+
+        let mut unwritten = vec![];
+        for ((scraper_id, received_at), records) in groups {
+            // the database is unavailable, the remaining updates are kept until it recovered
+            if !unwritten.is_empty() {
+                unwritten.push((scraper_id, received_at, records));
+                continue;
+            }
+
+            // only the updates that can't be written on their own are left out, see `ingest::write`
+            let failed = match ingest::write(pool, &records, scraper_id, received_at).await {
+                Ok(failed) => failed,
+                Err(_) => {
+                    unwritten.push((scraper_id, received_at, records));
+                    continue;
+                }
+            };
+            metrics::record_updates("written", records.len() - failed.len());
+            if failed.is_empty() {
+                continue;
+            }
+
+            tracing::warn!("Failed to write {} queued updates", failed.len());
+            metrics::record_updates("failed", failed.len());
+            let rejected: Vec<(String, APIError)> = failed
+                .into_iter()
+                .filter_map(|(index, e)| {
+                    serde_json::to_string(&records[index])
+                        .ok()
+                        .map(|payload| (payload, e))
+                })
+                .collect();
+            quarantine::store(pool, &rejected, scraper_id, "queue").await;
+        }
+
+        // leases of unwritten entries of the durable queue expire on their own
+        if !unwritten.is_empty() {
+            if !CONFIG.ingest.queue_durable {
+                let mut pending = self.pending.lock().unwrap();
+                let mut kept = 0;
+                for (scraper_id, received_at, records) in unwritten.into_iter().rev() {
+                    kept += records.len();
+                    for update in records.into_iter().rev() {
+                        pending.push_front(QueuedUpdate {
+                            update,
+                            scraper_id,
+                            received_at,
+                        });
+                    }
+                }
+                self.depth.fetch_sub(processed - kept, Ordering::SeqCst);
+            }
+            return Err(APIError::PoolError);
+        }
+
+        if CONFIG.ingest.queue_durable {
+            IngestQueueEntry::ack(pool, &ids).await?;
+            self.refresh_depth(pool).await?;
+        } else {
+            self.depth.fetch_sub(processed, Ordering::SeqCst);
+        }
+
+        Ok(ids.len().max(processed))
+    }
+
+    /// Take the next batch off the queue, with the ids of the leased entries of the durable queue
+    async fn pop(&self, pool: &DbPool) -> Result<(Vec<i64>, Vec<QueuedUpdate>), APIError> {
+        let config = &CONFIG.ingest;
+
+        if !config.queue_durable {
+            let mut pending = self.pending.lock().unwrap();
+            let count = pending.len().min(config.batch_records);
+            return Ok((vec![], pending.drain(..count).collect()));
+        }
+
+        let entries = IngestQueueEntry::lease(
+            pool,
+            config.batch_records as i64,
+            config.queue_lease_timeout,
+        )
+        .await?;
+
+        let mut ids = Vec::with_capacity(entries.len());
+        let mut batch = Vec::with_capacity(entries.len());
+        for entry in entries {
+            ids.push(entry.id);
+            // payloads were validated before they were queued, but may stem from an older version
+            match serde_json::from_str::<UpdateRequest>(&entry.payload) {
+                Ok(update) => batch.push(QueuedUpdate {
+                    update,
+                    scraper_id: entry.scraper_id,
+                    received_at: entry.enqueued_at,
+                }),
+                Err(e) => {
+                    tracing::warn!("Dropping unreadable queued update {}: {}", entry.id, e);
+                    metrics::record_updates("failed", 1);
+                }
+            }
+        }
+
+        Ok((ids, batch))
+    }
+
+    async fn refresh_depth(&self, pool: &DbPool) -> Result<(), APIError> {
+        let depth = IngestQueueEntry::depth(pool).await?;
+        self.depth.store(depth as usize, Ordering::SeqCst);
+
+        Ok(())
+    }
+}
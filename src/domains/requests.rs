@@ -1,21 +1,60 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug)]
-/// # FetchRequest
-/// Request sent by clients for fetching information about domains by a fqdn hash prefix.
-pub struct FetchRequest {
-    pub fqdn_hash: String,
+/// # FetchQuery
+/// Query sent by clients for fetching information about domains by a fqdn hash prefix,
+/// e.g. `/fetch?prefix=d774c9et&per_page=10`.
+/// Further pages are requested with the `next_cursor` of the previous response, `page` is deprecated
+/// and ignored if a cursor is supplied.
+pub struct FetchQuery {
+    pub prefix: String,
+    pub cursor: Option<String>,
+    /// related entries to embed in domain responses, e.g. `ruegen`
+    pub include: Option<String>,
+    #[serde(default)]
     pub page: u32,
+    #[serde(default = "default_per_page")]
     pub per_page: u32,
 }
 
 #[derive(Deserialize, Debug)]
+/// # BulkFetchRequest
+/// Request sent by clients for fetching information about domains by multiple fqdn hash prefixes at once.
+/// Pagination is applied to each prefix.
+pub struct BulkFetchRequest {
+    pub prefixes: Vec<String>,
+    /// related entries to embed in domain responses, e.g. `ruegen`
+    #[serde(default)]
+    pub include: Option<String>,
+    #[serde(default)]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub per_page: u32,
+}
+
+#[derive(Deserialize, Debug)]
+/// # PrefixListQuery
+/// Query sent by clients for downloading the prefix list, e.g. `/prefixes?since=42`.
+/// Clients supply the version of their local list to only receive the changes since then.
+pub struct PrefixListQuery {
+    pub since: Option<i32>,
+}
+
+fn default_per_page() -> u32 {
+    10
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 /// # UpdateRequest
 /// UpdateRequest sent by scrapers to the server with information about a domain and it's linked domains.
-/// The link array may only have a max of 100 elements. Please batch large domains to decrease load on the server. Otherwise your request may be rejected by the server for size reasons
+/// The link array is limited to `limits.links` elements, 100 by default. Please use crawl sessions for large domains to decrease load on the server.
 pub struct UpdateRequest {
     pub fqdn: String,
     pub network: bool,
     pub links: Vec<String>,
     pub last_updated: i64,
+    /// Canonical FQDN of the source, if the source redirects to or declares another host as canonical.
+    /// The source will then be merged into the canonical domain for scoring.
+    #[serde(default)]
+    pub canonical: Option<String>,
 }
@@ -1,13 +1,99 @@
+use crate::ruegen::responses::RuegeSummary;
 use serde::Serialize;
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct DomainResponse {
     pub fqdn: String,
+    /// canonical domain if `fqdn` is an alias, e.g. `spiegel.de` for `www.spiegel.de`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
     pub score: [f32; 2],
     pub last_updated: i64,
+    /// rügen linked to the domain, only present if requested with `include=ruegen`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ruegen: Option<Vec<RuegeSummary>>,
 }
 
 #[derive(Serialize)]
 pub struct AggregatedDomainResponse {
     pub domains: Vec<DomainResponse>,
+    /// cursor of the next page, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// filler to pad the response to a fixed size, see `PaddingConfig`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding: Option<String>,
+}
+
+/// Domains matching a single prefix of a batch request
+#[derive(Serialize)]
+pub struct PrefixDomains {
+    /// prefix as sent by the client
+    pub prefix: String,
+    pub domains: Vec<DomainResponse>,
+}
+
+#[derive(Serialize)]
+pub struct BatchDomainResponse {
+    /// results in the order of the requested prefixes
+    pub results: Vec<PrefixDomains>,
+    /// filler to pad the response to a fixed size, see `PaddingConfig`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding: Option<String>,
+}
+
+/// Prefix of a fqdn hash with the score band of each network, serialized as
+/// `["d774c9e3", [2, 0]]` to keep the list compact
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PrefixEntry(pub String, pub [u8; 2]);
+
+#[derive(Serialize, Debug)]
+pub struct PrefixListResponse {
+    /// id of the scoring run the list was built from
+    pub version: i32,
+    /// unix timestamp the list was published at
+    pub published_at: i64,
+    pub prefix_length: usize,
+    /// lower bounds of the score bands
+    pub bands: Vec<f32>,
+    /// entries sorted by prefix
+    pub entries: Vec<PrefixEntry>,
+}
+
+/// Entries that changed since the version of the client
+#[derive(Serialize, Debug)]
+pub struct PrefixDeltaResponse {
+    pub since: i32,
+    pub version: i32,
+    pub added: Vec<PrefixEntry>,
+    pub changed: Vec<PrefixEntry>,
+    pub removed: Vec<String>,
+}
+
+/// Either the full prefix list or the changes since the version of the client
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PrefixListUpdate<'a> {
+    Full(&'a PrefixListResponse),
+    Delta(&'a PrefixDeltaResponse),
+}
+
+/// Summary of a bulk ingestion
+#[derive(Serialize, Default)]
+pub struct IngestResponse {
+    /// count of lines in the payload, including empty ones
+    pub lines: usize,
+    /// count of records written to the graph
+    pub accepted: usize,
+    pub rejected: Vec<RejectedLine>,
+}
+
+/// Line of a bulk ingestion that wasn't written to the graph
+#[derive(Serialize)]
+pub struct RejectedLine {
+    /// line number in the payload, starting at 1
+    pub line: usize,
+    /// field that failed the validation, if any
+    pub loc: Option<[&'static str; 1]>,
+    pub msg: String,
 }
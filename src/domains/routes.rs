@@ -1,111 +1,333 @@
-use super::requests::{FetchRequest, UpdateRequest};
-use crate::core::config::{CONFIG, PER_PAGE_ERROR};
+use super::requests::{BulkFetchRequest, FetchQuery, PrefixListQuery, UpdateRequest};
+use super::responses::{DomainResponse, PrefixDomains, PrefixListUpdate};
+use super::util::{
+    anonymize_prefix, anonymize_prefixes, collapse_prefixes, decode_cursor, fetch_cacheable,
+    includes_ruegen, validate_per_page, validate_prefix, validate_update,
+};
+use crate::core::auth::Scraper;
+use crate::core::caching::Cacheable;
+use crate::core::config::CONFIG;
 use crate::core::errors::APIError;
-use crate::core::types::APIResponse;
-use crate::db::models::{Domain, DomainLink, SimpleDomain};
+use crate::core::idempotency;
+use crate::core::types::{APIResponse, Format, Negotiated};
+use crate::core::util::timestamp;
+use crate::db::models::{CrawlFrontier, DomainAlias, DomainLink, SimpleDomain};
 use crate::db::util::DbPool;
-use crate::domains::responses::AggregatedDomainResponse;
+use crate::domains::ingest::Ingestion;
+use crate::domains::padding;
+use crate::domains::prefixes::PrefixListCache;
+use crate::domains::quarantine;
+use crate::domains::queue::IngestQueue;
 use actix_web::web::Data;
 use actix_web::{
     get, post,
-    web::{Json, ServiceConfig},
-    HttpResponse,
+    web::{Json, Payload, Query, ServiceConfig},
+    HttpMessage, HttpRequest, HttpResponse, Responder,
 };
-use lazy_static::lazy_static;
-use regex::Regex;
+use futures::StreamExt;
+use std::collections::HashMap;
 
 #[get("/fetch")]
-async fn fetch(data: Json<FetchRequest>, pool: Data<DbPool>) -> APIResponse {
-    if data.fqdn_hash.len() < 64 {
-        return Err(APIError::ValidationError(
-            ["fqdn_hash"],
-            "Hash prefix shouldn't be longer than 64 bytes".to_owned(),
-        ));
-    } else if data.per_page > CONFIG.database.domains.per_page {
-        return Err(APIError::ValidationError(
-            ["per_page"],
-            PER_PAGE_ERROR.clone(),
-        ));
-    } else if !data
-        .fqdn_hash
-        .chars()
-        .all(|c| c.is_ascii_alphabetic() || c.is_ascii_digit() || c.is_ascii_lowercase())
+async fn fetch(req: HttpRequest, query: Query<FetchQuery>, pool: Data<DbPool>) -> APIResponse {
+    validate_per_page(query.per_page)?;
+    validate_prefix("prefix", &query.prefix)?;
+    let with_ruegen = includes_ruegen(&query.include)?;
+
+    // prefixes matching too few domains are rejected even if the client has a cached response
+    let prefix = anonymize_prefix(pool.as_ref(), "prefix", &query.prefix).await?;
+
+    let cacheable =
+        fetch_cacheable(pool.as_ref(), &query, &prefix, Format::negotiate(&req)).await?;
+    if let Some(response) = cacheable.not_modified(&req) {
+        return Ok(response);
+    }
+
+    let cursor = decode_cursor(&query.cursor)?;
+
+    let (domains, next) = SimpleDomain::by_hash(
+        pool.as_ref(),
+        &prefix,
+        query.per_page,
+        query.page,
+        cursor.as_ref(),
+    )
+    .await?;
+
+    let response = padding::respond(
+        &req,
+        pool.as_ref(),
+        domains,
+        next.map(|next| next.encode()),
+        1,
+        with_ruegen,
+    )
+    .await?;
+
+    Ok(cacheable.apply(response))
+}
+
+#[post("/fetch")]
+async fn bulk_fetch(
+    req: HttpRequest,
+    data: Json<BulkFetchRequest>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    validate_per_page(data.per_page)?;
+    let with_ruegen = includes_ruegen(&data.include)?;
+    let hashes =
+        collapse_prefixes(anonymize_prefixes(pool.as_ref(), "prefixes", &data.prefixes).await?);
+
+    let domains = SimpleDomain::by_hashes(pool.as_ref(), &hashes, data.per_page, data.page)
+        .await?
+        .into_iter()
+        .map(|(_, domain)| domain)
+        .collect();
+
+    padding::respond(
+        &req,
+        pool.as_ref(),
+        domains,
+        None,
+        data.prefixes.len(),
+        with_ruegen,
+    )
+    .await
+}
+
+/// Like the bulk fetch, but the domains are grouped by the requested prefix
+#[post("/batch")]
+async fn batch_fetch(
+    req: HttpRequest,
+    data: Json<BulkFetchRequest>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    validate_per_page(data.per_page)?;
+    let with_ruegen = includes_ruegen(&data.include)?;
+    let anonymized = anonymize_prefixes(pool.as_ref(), "prefixes", &data.prefixes).await?;
+
+    // overlapping prefixes are still queried on their own, as every prefix is paginated separately
+    let mut queried = anonymized.clone();
+    queried.sort();
+    queried.dedup();
+
+    let mut matches: HashMap<String, Vec<DomainResponse>> = HashMap::new();
+    for (prefix, domain) in
+        SimpleDomain::by_hashes(pool.as_ref(), &queried, data.per_page, data.page).await?
     {
-        return Err(APIError::ValidationError(
-            ["fqdn_hash"],
-            "Hash Prefix may only consist of ascii characters".to_owned(),
-        ));
+        matches.entry(prefix).or_default().push(domain);
     }
 
-    let domains =
-        SimpleDomain::by_hash(pool.as_ref(), &data.fqdn_hash, data.per_page, data.page).await?;
+    // a widened prefix may be shared by multiple requested prefixes
+    let results = data
+        .prefixes
+        .iter()
+        .zip(anonymized)
+        .map(|(prefix, anonymized)| PrefixDomains {
+            prefix: prefix.clone(),
+            domains: matches.get(&anonymized).cloned().unwrap_or_default(),
+        })
+        .collect();
+
+    padding::respond_batch(&req, pool.as_ref(), results, with_ruegen).await
+}
+
+/// Prefix list of all scored domains for offline lookups. Clients check the hash of a visited
+/// domain against the list and only fetch details for matching prefixes.
+/// Clients supplying the version of their local list only receive the changes since then.
+#[get("/prefixes")]
+async fn prefixes(
+    req: HttpRequest,
+    query: Query<PrefixListQuery>,
+    pool: Data<DbPool>,
+    cache: Data<PrefixListCache>,
+) -> APIResponse {
+    let list = match cache.latest(pool.as_ref()).await? {
+        Some(list) => list,
+        None => return Err(APIError::NotFoundError),
+    };
+
+    let since = query
+        .since
+        .map(|since| since.to_string())
+        .unwrap_or_default();
+    let cacheable = Cacheable::new(
+        list.version,
+        Some(list.published_at),
+        &[&since, Format::negotiate(&req).content_type()],
+    );
+    if let Some(response) = cacheable.not_modified(&req) {
+        return Ok(response);
+    }
 
-    match domains.len() {
-        0 => Err(APIError::NotFoundError),
-        _ => Ok(HttpResponse::Ok().json(AggregatedDomainResponse { domains })),
+    if let Some(since) = query.since {
+        if let Some(delta) = cache.delta(pool.as_ref(), &list, since).await? {
+            return Ok(
+                cacheable.apply(Negotiated::ok(PrefixListUpdate::Delta(&delta)).respond_to(&req))
+            );
+        }
     }
+
+    Ok(cacheable.apply(Negotiated::ok(PrefixListUpdate::Full(&list)).respond_to(&req)))
 }
 
 #[post("/update")]
-async fn update(data: Json<UpdateRequest>, pool: Data<DbPool>) -> APIResponse {
-    // basic check if supplied source is a valid FQDN
-    // see the attribution in the lazy_static reference for a complete explanation
-    let regex_match_len = FQDN_REGEX.shortest_match(&data.fqdn);
-    if regex_match_len.is_none() || regex_match_len.unwrap() != data.fqdn.len() {
-        // If we have an invalid fqdn reject request
-        return Err(APIError::ValidationError(
-            ["fqdn"],
-            "The supplied source is not a valid fqdn".to_owned(),
-        ));
-    }
+async fn update(
+    req: HttpRequest,
+    scraper: Scraper,
+    data: Json<UpdateRequest>,
+    queue: Data<IngestQueue>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    // an invalid header isn't a problem of the payload and hence not quarantined
+    let key = idempotency::key(&req)?;
 
-    // pre-convert the pool data to a pool reference for re-usability
-    let pool_ref = pool.as_ref();
-
-    // get or create source domain entry. The grace condition handling is kinda bodged … Feel free to improve
-    let source = match SimpleDomain::get_by_fqdn(pool_ref, &data.fqdn).await {
-        Ok(domain) => domain,
-        Err(e) => match e {
-            APIError::NotFoundError => {
-                match SimpleDomain::create(pool_ref, data.last_updated, &data.fqdn).await {
-                    Ok(domain) => domain,
-                    Err(APIError::IntegrityError) => {
-                        SimpleDomain::get_by_fqdn(pool_ref, &data.fqdn).await?
-                    }
-                    Err(err) => {
-                        return Err(err);
-                    }
-                }
-            }
-            err => {
-                return Err(err);
-            }
-        },
+    let response = match validate_update(&data) {
+        Ok(()) => submit_once(pool.as_ref(), &queue, &data, key, scraper.id()).await,
+        Err(e) => Err(e),
     };
 
-    // Optionally refresh last_updated value of source domain
-    if source.last_updated != data.last_updated {
-        source.refresh(pool_ref, data.last_updated).await?;
+    // rejected payloads are kept for debugging the scraper
+    if let Err(e) = &response {
+        quarantine::store_updates(
+            pool.as_ref(),
+            std::slice::from_ref(&*data),
+            e,
+            scraper.id(),
+            "update",
+        )
+        .await;
+    }
+
+    response
+}
+
+/// Submit a validated update, retries of a submission with the same key replay the result of the
+/// first one
+async fn submit_once(
+    pool: &DbPool,
+    queue: &IngestQueue,
+    data: &UpdateRequest,
+    key: Option<String>,
+    scraper_id: Option<i32>,
+) -> APIResponse {
+    match key {
+        Some(key) => {
+            let fingerprint = idempotency::fingerprint(data)?;
+            idempotency::once(
+                pool,
+                &key,
+                scraper_id,
+                &fingerprint,
+                submit_update(pool, queue, data, scraper_id),
+            )
+            .await
+        }
+        None => submit_update(pool, queue, data, scraper_id).await,
+    }
+}
+
+/// Queue a validated update, see [`IngestQueue`]. `202 Accepted` only means the update was queued,
+/// stale updates are skipped with their links once written, see [`SimpleDomain::ingest`]. Without
+/// a queue the update is written right away and stale updates are a conflict.
+pub async fn submit_update(
+    pool: &DbPool,
+    queue: &IngestQueue,
+    data: &UpdateRequest,
+    scraper_id: Option<i32>,
+) -> APIResponse {
+    if !IngestQueue::enabled() {
+        return apply_update(pool, data, scraper_id).await;
+    }
+
+    queue.push(pool, data.clone(), scraper_id).await?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Write a validated update to the graph, attributing its links to `scraper_id`
+async fn apply_update(pool: &DbPool, data: &UpdateRequest, scraper_id: Option<i32>) -> APIResponse {
+    let received_at = timestamp();
+    let source =
+        SimpleDomain::upsert(pool, &data.fqdn, data.network, data.last_updated, true).await?;
+
+    // a retried or delayed submission must not apply an older crawl over a newer one
+    if source.last_updated > data.last_updated {
+        return Err(APIError::ConflictError(format!(
+            "{} was already updated with a newer crawl from {}",
+            source.fqdn, source.last_updated
+        )));
+    }
+
+    // the update acknowledges a lease of the source, see `crawl::frontier`
+    CrawlFrontier::ack(pool, source.id).await?;
+
+    // register redirects or canonical hosts so the source is scored as its canonical domain
+    if let Some(canonical) = &data.canonical {
+        let canonical =
+            SimpleDomain::upsert(pool, canonical, data.network, data.last_updated, false).await?;
+        DomainAlias::upsert(pool, source.id, canonical.id).await?;
     }
 
     for link in &data.links {
-        DomainLink::upsert(
-            pool_ref,
-            source.id,
-            Domain::get_or_create(pool_ref, link, data.last_updated).await?,
-        )
-        .await?;
+        let target =
+            SimpleDomain::upsert(pool, link, data.network, data.last_updated, false).await?;
+        DomainLink::upsert(pool, source.id, target.id, scraper_id, received_at).await?;
     }
 
     Ok(HttpResponse::Accepted().finish())
 }
 
+/// Bulk variant of `/update` for line-delimited payloads, see [`Ingestion`]
+#[post("/ingest")]
+async fn ingest(
+    req: HttpRequest,
+    scraper: Scraper,
+    mut payload: Payload,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    if req.content_type() != "application/x-ndjson" {
+        return Err(APIError::ValidationError(
+            ["body"],
+            "Bulk ingestion expects an application/x-ndjson body".to_owned(),
+        ));
+    }
+
+    let mut ingestion = Ingestion::new(pool.as_ref(), scraper.id());
+    let mut buffer: Vec<u8> = vec![];
+
+    // records are processed while the payload is still streamed in
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| APIError::ValidationError(["body"], e.to_string()))?;
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            ingestion.line(&line).await?;
+        }
+        // each line is a single update, which is limited like a JSON body
+        if buffer.len() > CONFIG.limits.body_bytes {
+            return Err(APIError::ValidationError(
+                ["body"],
+                format!(
+                    "A line exceeds the limit of {} bytes",
+                    CONFIG.limits.body_bytes
+                ),
+            ));
+        }
+    }
+    // the last line may not be terminated
+    if !buffer.is_empty() {
+        ingestion.line(&buffer).await?;
+    }
+
+    Ok(HttpResponse::Ok().json(ingestion.finish().await?))
+}
+
 pub fn services(cfg: &mut ServiceConfig) {
     cfg.service(update);
+    cfg.service(ingest);
     cfg.service(fetch);
-}
-
-lazy_static! {
-    // Domain regex from Anton Nikiforov as published on https://stackoverflow.com/a/44534191 under CC-By-Sa 3.0 with SA being satisfied by the AGPL
-    static ref FQDN_REGEX: Regex = Regex::new(r"^(?!:\/\/)(?=.{1,255}$)((.{1,63}\.){1,127}(?![0-9]*$)[a-z0-9-]+\.?)$").unwrap();
+    cfg.service(bulk_fetch);
+    cfg.service(batch_fetch);
+    cfg.service(prefixes);
 }
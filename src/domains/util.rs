@@ -1 +1,248 @@
+use super::requests::{FetchQuery, UpdateRequest};
+use crate::core::caching::Cacheable;
+use crate::core::config::{
+    BATCH_PREFIXES_ERROR, CONFIG, K_ANONYMITY_ERROR, PER_PAGE_ERROR, PREFIX_LENGTH_ERROR,
+};
+use crate::core::errors::APIError;
+use crate::core::limits;
+use crate::core::types::Format;
+use crate::db::models::{ScoreRun, SimpleDomain};
+use crate::db::util::{Cursor, DbPool};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
 
+/// Validate a fqdn hash prefix sent by clients. `loc` is the field the prefix was supplied in
+pub fn validate_prefix(loc: &'static str, prefix: &str) -> Result<(), APIError> {
+    let config = &CONFIG.database.domains;
+
+    if prefix.len() < config.min_prefix_length || prefix.len() > config.max_prefix_length {
+        Err(APIError::ValidationError(
+            [loc],
+            PREFIX_LENGTH_ERROR.clone(),
+        ))
+    } else if !prefix
+        .chars()
+        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+    {
+        Err(APIError::ValidationError(
+            [loc],
+            "Hash prefix may only consist of lowercase hex characters".to_owned(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Ensure a prefix matches at least k domains, so the server can't narrow a request down to a
+/// single site. Depending on the configuration prefixes matching fewer domains are either
+/// widened by dropping trailing characters or rejected.
+/// Returns the prefix that should be used for the query.
+pub async fn anonymize_prefix(
+    pool: &DbPool,
+    loc: &'static str,
+    prefix: &str,
+) -> Result<String, APIError> {
+    let mut anonymized = anonymize(pool, loc, &[prefix.to_owned()]).await?;
+
+    Ok(anonymized.remove(0))
+}
+
+/// Validate and anonymize all prefixes of a bulk request, see [`anonymize_prefix`].
+/// Returns the prefix that should be used for the query of each requested prefix.
+pub async fn anonymize_prefixes(
+    pool: &DbPool,
+    loc: &'static str,
+    prefixes: &[String],
+) -> Result<Vec<String>, APIError> {
+    if prefixes.len() > CONFIG.database.domains.max_batch_prefixes {
+        return Err(APIError::ValidationError(
+            [loc],
+            BATCH_PREFIXES_ERROR.clone(),
+        ));
+    }
+    for prefix in prefixes {
+        validate_prefix(loc, prefix)?;
+    }
+
+    anonymize(pool, loc, prefixes).await
+}
+
+/// Count the domains of every length the prefixes may be widened to with a single query and pick the longest one
+/// matching at least k domains
+async fn anonymize(
+    pool: &DbPool,
+    loc: &'static str,
+    prefixes: &[String],
+) -> Result<Vec<String>, APIError> {
+    let config = &CONFIG.database.domains;
+    // candidates of a prefix from the longest to the shortest one
+    let candidates = |prefix: &str| -> Vec<String> {
+        let shortest = match config.widen_prefixes {
+            true => config.min_prefix_length.min(prefix.len()),
+            false => prefix.len(),
+        };
+        (shortest..=prefix.len())
+            .rev()
+            .map(|length| prefix[..length].to_owned())
+            .collect()
+    };
+
+    let mut queried: Vec<String> = prefixes
+        .iter()
+        .flat_map(|prefix| candidates(prefix))
+        .collect();
+    queried.sort();
+    queried.dedup();
+    let counts = SimpleDomain::count_by_hashes(pool, &queried, config.k_anonymity).await?;
+    let counts: HashMap<String, i64> = queried.into_iter().zip(counts).collect();
+
+    prefixes
+        .iter()
+        .map(|prefix| {
+            candidates(prefix)
+                .into_iter()
+                .find(|candidate| counts[candidate] >= config.k_anonymity as i64)
+                .ok_or_else(|| APIError::ValidationError([loc], K_ANONYMITY_ERROR.clone()))
+        })
+        .collect()
+}
+
+/// Sort and deduplicate anonymized prefixes of a bulk request. Widened prefixes may overlap, prefixes starting with
+/// another prefix of the request are dropped since their domains are already matched by the shorter one
+pub fn collapse_prefixes(mut prefixes: Vec<String>) -> Vec<String> {
+    prefixes.sort();
+
+    // after sorting all prefixes starting with a prefix directly follow it
+    let mut collapsed: Vec<String> = Vec::with_capacity(prefixes.len());
+    for prefix in prefixes {
+        match collapsed.last() {
+            Some(shorter) if prefix.starts_with(shorter.as_str()) => {}
+            _ => collapsed.push(prefix),
+        }
+    }
+
+    collapsed
+}
+
+/// HTTP caching of a fetch by prefix, see [`Cacheable`]. `prefix` is the anonymized prefix of the query
+pub async fn fetch_cacheable(
+    pool: &DbPool,
+    query: &FetchQuery,
+    prefix: &str,
+    format: Format,
+) -> Result<Cacheable, APIError> {
+    let run = ScoreRun::latest(pool).await?;
+
+    Ok(Cacheable::new(
+        run.as_ref().map_or(0, |run| run.id),
+        run.and_then(|run| run.finished_at),
+        &[
+            prefix,
+            query.cursor.as_deref().unwrap_or_default(),
+            query.include.as_deref().unwrap_or_default(),
+            &query.page.to_string(),
+            &query.per_page.to_string(),
+            format.content_type(),
+        ],
+    ))
+}
+
+/// Check the related entries requested to be embedded in domain responses.
+/// Returns if rügen should be embedded, the only supported include for now
+pub fn includes_ruegen(include: &Option<String>) -> Result<bool, APIError> {
+    match include.as_deref() {
+        None | Some("") => Ok(false),
+        Some("ruegen") => Ok(true),
+        Some(_) => Err(APIError::ValidationError(
+            ["include"],
+            "Unknown include, only ruegen is supported".to_owned(),
+        )),
+    }
+}
+
+/// Decode the pagination cursor sent by clients
+pub fn decode_cursor(cursor: &Option<String>) -> Result<Option<Cursor>, APIError> {
+    match cursor {
+        Some(cursor) => match Cursor::decode(cursor) {
+            Some(cursor) => Ok(Some(cursor)),
+            None => Err(APIError::ValidationError(
+                ["cursor"],
+                "Invalid cursor, please use the next_cursor of a previous response".to_owned(),
+            )),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Validate the requested page size against the limit of this instance
+pub fn validate_per_page(per_page: u32) -> Result<(), APIError> {
+    if per_page > CONFIG.database.domains.per_page {
+        Err(APIError::ValidationError(
+            ["per_page"],
+            PER_PAGE_ERROR.clone(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Parse and validate a serialized update, e.g. a line of a bulk ingestion
+pub fn parse_update(payload: &[u8]) -> Result<UpdateRequest, APIError> {
+    let data =
+        serde_json::from_slice::<UpdateRequest>(payload).map_err(|e| APIError::from_json(&e))?;
+    validate_update(&data)?;
+
+    Ok(data)
+}
+
+/// Validate an update sent by scrapers
+pub fn validate_update(data: &UpdateRequest) -> Result<(), APIError> {
+    limits::check_fqdn("fqdn", &data.fqdn)?;
+    limits::check_links(&data.links)?;
+    if let Some(canonical) = &data.canonical {
+        limits::check_fqdn("canonical", canonical)?;
+    }
+
+    // basic check if supplied source is a valid FQDN
+    if !is_fqdn(&data.fqdn) {
+        // If we have an invalid fqdn reject request
+        return Err(APIError::ValidationError(
+            ["fqdn"],
+            "The supplied source is not a valid fqdn".to_owned(),
+        ));
+    }
+
+    if let Some(canonical) = &data.canonical {
+        if !is_fqdn(canonical) {
+            return Err(APIError::ValidationError(
+                ["canonical"],
+                "The supplied canonical host is not a valid fqdn".to_owned(),
+            ));
+        } else if canonical == &data.fqdn {
+            return Err(APIError::ValidationError(
+                ["canonical"],
+                "A domain can't be an alias of itself".to_owned(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check if the supplied string is a valid FQDN
+/// see the attribution in the lazy_static reference for a complete explanation
+pub fn is_fqdn(fqdn: &str) -> bool {
+    // the regex crate has no look-around, so the length, scheme and numeric tld checks are done here
+    let tld = fqdn.rsplit('.').next().unwrap_or_default();
+    !fqdn.is_empty()
+        && fqdn.len() <= 255
+        && !fqdn.starts_with("://")
+        && (fqdn.ends_with('.') || !tld.bytes().all(|b| b.is_ascii_digit()))
+        && FQDN_REGEX.is_match(fqdn)
+}
+
+lazy_static! {
+    // Domain regex from Anton Nikiforov as published on https://stackoverflow.com/a/44534191 under CC-By-Sa 3.0 with SA being satisfied by the AGPL
+    static ref FQDN_REGEX: Regex = Regex::new(r"^(.{1,63}\.){1,127}[a-z0-9-]+\.?$").unwrap();
+}
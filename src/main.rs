@@ -10,10 +10,14 @@ use std::io;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::Registry;
 
+mod admin;
+mod commands;
 mod core;
+mod crawl;
 mod db;
 mod domains;
 mod ruegen;
+mod scoring;
 
 #[cfg(test)]
 mod tests;
@@ -38,6 +42,11 @@ fn greet_user(username: &str) -> String {
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(command) = args.get(1) {
+        return commands::run(command, &args[2..]).await;
+    }
+
     // Start a new jaeger trace pipeline
     global::set_text_map_propagator(Propagator::new());
 
@@ -78,6 +87,32 @@ async fn main() -> io::Result<()> {
         }
     }
 
+    // runs can't survive a restart since they are executed in-process, runs of other instances keep their heartbeat
+    if let Err(e) =
+        db::models::ScoreRun::fail_interrupted(&pool, CONFIG.scoring.heartbeat_timeout).await
+    {
+        eprintln!(
+            "Failed to mark interrupted scoring runs as failed: {}",
+            String::from(e)
+        );
+    }
+
+    // shared across all workers to only ever execute a single scoring run
+    let scoring_runner = Data::new(scoring::runner::ScoringRunner::new());
+    let prefix_list_cache = Data::new(domains::prefixes::PrefixListCache::default());
+    let frontier = Data::new(crawl::frontier::Frontier::default());
+    let ingest_queue = Data::new(domains::queue::IngestQueue::default());
+    if domains::queue::IngestQueue::enabled() {
+        domains::metrics::observe_depth(ingest_queue.clone());
+        domains::queue::IngestQueue::spawn_workers(ingest_queue.clone(), pool.clone()).await;
+    }
+    // the server takes ownership of both, they are still needed to drain the queue on shutdown
+    let (drained_queue, drain_pool) = (ingest_queue.clone(), pool.clone());
+
+    if CONFIG.admin.token.is_none() {
+        tracing::warn!("BACKEND_ADMIN_TOKEN isn't set, the admin API is disabled");
+    }
+
     HttpServer::new(move || {
         App::new()
             .wrap(RequestTracing::new())
@@ -88,14 +123,50 @@ async fn main() -> io::Result<()> {
             .service(
                 web::scope("/v1")
                     .app_data(Data::new(pool.clone()))
+                    .app_data(prefix_list_cache.clone())
+                    .app_data(frontier.clone())
+                    .app_data(ingest_queue.clone())
+                    .app_data(
+                        web::QueryConfig::default()
+                            .error_handler(core::errors::query_error_handler),
+                    )
+                    .app_data(
+                        web::JsonConfig::default()
+                            .limit(CONFIG.limits.body_bytes)
+                            .error_handler(core::errors::json_error_handler),
+                    )
                     .service(web::scope("/domains").configure(domains::routes::services))
-                    .service(web::scope("/ruegen").configure(ruegen::routes::services)),
+                    .service(web::scope("/ruegen").configure(ruegen::routes::services))
+                    .service(web::scope("/crawl").configure(crawl::routes::services)),
             )
+            .configure(|cfg| {
+                if CONFIG.admin.token.is_some() {
+                    cfg.service(
+                        web::scope("/admin")
+                            .app_data(Data::new(pool.clone()))
+                            .app_data(scoring_runner.clone())
+                            .app_data(ingest_queue.clone())
+                            .app_data(
+                                web::QueryConfig::default()
+                                    .error_handler(core::errors::query_error_handler),
+                            )
+                            .app_data(
+                                web::JsonConfig::default()
+                                    .limit(CONFIG.limits.body_bytes)
+                                    .error_handler(core::errors::json_error_handler),
+                            )
+                            .configure(admin::routes::services),
+                    );
+                }
+            })
     })
     .bind(format!("{}:{}", CONFIG.api.host, CONFIG.api.port))?
     .run()
     .await?;
 
+    // updates accepted by the in-process queue must not be lost
+    drained_queue.drain(&drain_pool).await;
+
     // Ensure all spans have been reported
     opentelemetry::global::shutdown_tracer_provider();
 
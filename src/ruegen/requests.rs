@@ -1,8 +1,2 @@
-use serde::Deserialize;
-
-#[derive(Deserialize)]
-pub struct FetchRequest {
-    pub fqdn: String,
-    pub page: u32,
-    pub per_page: u32,
-}
+// Rügen are fetched by the same hash prefix pattern as domains
+pub use crate::domains::requests::{BulkFetchRequest, FetchQuery};
@@ -8,3 +8,20 @@ pub struct RuegenInformation {
     pub ziffer: String,
     pub year: u16,
 }
+
+/// Compact form of a rüge embedded in domain responses
+#[derive(Serialize, Clone, Debug)]
+pub struct RuegeSummary {
+    pub identifier: String,
+    pub title: String,
+    pub ziffer: String,
+    pub year: u16,
+}
+
+#[derive(Serialize)]
+pub struct AggregatedRuegenResponse {
+    pub ruegen: Vec<RuegenInformation>,
+    /// cursor of the next page, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
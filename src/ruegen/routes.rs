@@ -1,22 +1,81 @@
-use crate::{core::types::APIResponse, ruegen::responses::RuegenInformation};
+use super::requests::{BulkFetchRequest, FetchQuery};
+use super::responses::AggregatedRuegenResponse;
+use crate::core::errors::APIError;
+use crate::core::types::{APIResponse, Negotiated};
+use crate::db::models::Ruege;
+use crate::db::util::DbPool;
+use crate::domains::util::{
+    anonymize_prefix, anonymize_prefixes, collapse_prefixes, decode_cursor, validate_per_page,
+    validate_prefix,
+};
+use crate::ruegen::responses::RuegenInformation;
 use actix_web::{
     get, post,
-    web::{Json, ServiceConfig},
+    web::{Data, Json, Query, ServiceConfig},
+    HttpRequest, Responder,
 };
 
 #[get("/fetch")]
 #[tracing::instrument]
-pub async fn fetch() -> APIResponse {
-    todo!()
+pub async fn fetch(req: HttpRequest, query: Query<FetchQuery>, pool: Data<DbPool>) -> APIResponse {
+    validate_per_page(query.per_page)?;
+    validate_prefix("prefix", &query.prefix)?;
+    let prefix = anonymize_prefix(pool.as_ref(), "prefix", &query.prefix).await?;
+
+    let cursor = decode_cursor(&query.cursor)?;
+
+    let (ruegen, next) = Ruege::by_hash(
+        pool.as_ref(),
+        &prefix,
+        query.per_page,
+        query.page,
+        cursor.as_ref(),
+    )
+    .await?;
+
+    match ruegen.len() {
+        0 => Err(APIError::NotFoundError),
+        _ => Ok(Negotiated::ok(AggregatedRuegenResponse {
+            ruegen,
+            next_cursor: next.map(|next| next.encode()),
+        })
+        .respond_to(&req)),
+    }
+}
+
+#[post("/fetch")]
+#[tracing::instrument]
+pub async fn bulk_fetch(
+    req: HttpRequest,
+    data: Json<BulkFetchRequest>,
+    pool: Data<DbPool>,
+) -> APIResponse {
+    validate_per_page(data.per_page)?;
+    let prefixes =
+        collapse_prefixes(anonymize_prefixes(pool.as_ref(), "prefixes", &data.prefixes).await?);
+
+    let ruegen = Ruege::by_hashes(pool.as_ref(), &prefixes, data.per_page, data.page).await?;
+
+    match ruegen.len() {
+        0 => Err(APIError::NotFoundError),
+        _ => Ok(Negotiated::ok(AggregatedRuegenResponse {
+            ruegen,
+            next_cursor: None,
+        })
+        .respond_to(&req)),
+    }
 }
 
 #[post("/update")]
 #[tracing::instrument]
 pub async fn update(_ruege: Json<RuegenInformation>) -> APIResponse {
-    todo!()
+    Err(APIError::NotImplementedError(
+        "Rügen can't be submitted yet".to_owned(),
+    ))
 }
 
 pub fn services(cfg: &mut ServiceConfig) {
     cfg.service(update);
     cfg.service(fetch);
+    cfg.service(bulk_fetch);
 }
@@ -0,0 +1,3 @@
+pub mod diff;
+pub mod metrics;
+pub mod runner;
@@ -0,0 +1,396 @@
+use crate::core::errors::APIError;
+use crate::db::models::{DomainLink, DomainScore, ScoreRun, ScoreRunState, Seed, SimpleDomain};
+use crate::db::util::DbPool;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Count of referrers reported as cause for each mover
+const MAX_REFERRER_CAUSES: usize = 3;
+/// Default count of risers and fallers listed per network
+pub const DEFAULT_LIMIT: usize = 10;
+/// Maximal count of risers and fallers listed per network
+pub const MAX_LIMIT: usize = 100;
+
+#[derive(Serialize, Debug)]
+pub struct DiffReport {
+    pub from_run: i32,
+    pub to_run: i32,
+    pub networks: Vec<NetworkDiff>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NetworkDiff {
+    pub network: bool,
+    /// Kendall rank correlation (tau-b) of all domains scored in either run
+    pub kendall_tau: f64,
+    pub risers: Vec<Mover>,
+    pub fallers: Vec<Mover>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Mover {
+    pub domain_id: i32,
+    pub fqdn: String,
+    pub from: f32,
+    pub to: f32,
+    pub delta: f32,
+    pub causes: Vec<Cause>,
+}
+
+/// Likely cause of a score change
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Cause {
+    /// the domain was added to the seeds of the network
+    SeedAdded,
+    /// the domain was removed from the seeds of the network
+    SeedRemoved,
+    /// the contribution of a referrer changed. It is estimated from the current links of the referrer since links
+    /// aren't versioned per run
+    Referrer {
+        domain_id: i32,
+        fqdn: String,
+        contribution_delta: f32,
+        seed_changed: bool,
+    },
+}
+
+/// `(domain_id, from, to, causes)` of a mover whose fqdns aren't fetched yet
+type UnresolvedMover = (i32, f32, f32, Vec<Cause>);
+
+/// Compare the scores of two finished runs
+pub async fn report(
+    pool: &DbPool,
+    from: i32,
+    to: i32,
+    limit: usize,
+) -> Result<DiffReport, APIError> {
+    // scores of unfinished runs are incomplete, so their movers would be bogus
+    for id in [from, to] {
+        let run = ScoreRun::get(pool, id).await?;
+        if run.state != ScoreRunState::Finished.as_str() {
+            return Err(APIError::ConflictError(format!(
+                "Run {} is {}, only finished runs can be compared",
+                id, run.state
+            )));
+        }
+    }
+
+    let from_scores = scores_by_domain(DomainScore::by_run(pool, from).await?);
+    let to_scores = scores_by_domain(DomainScore::by_run(pool, to).await?);
+    let from_seeds = Seed::by_run(pool, from).await?;
+    let to_seeds = Seed::by_run(pool, to).await?;
+
+    // risers and fallers of each network with the correlation of both runs
+    let mut ranked = vec![];
+    for network in [true, false] {
+        let index = !network as usize;
+
+        let mut deltas: Vec<(i32, f32, f32)> = from_scores
+            .keys()
+            .chain(to_scores.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|id| {
+                (
+                    *id,
+                    from_scores.get(id).map_or(0.0, |score| score[index]),
+                    to_scores.get(id).map_or(0.0, |score| score[index]),
+                )
+            })
+            .collect();
+
+        let mut pairs: Vec<(f32, f32)> = deltas.iter().map(|(_, from, to)| (*from, *to)).collect();
+        let kendall_tau = kendall_tau(&mut pairs);
+
+        deltas.sort_by(|a, b| {
+            (b.2 - b.1)
+                .partial_cmp(&(a.2 - a.1))
+                .unwrap_or(Ordering::Equal)
+        });
+        let risers: Vec<_> = deltas
+            .iter()
+            .take(limit)
+            .filter(|(_, from, to)| to > from)
+            .copied()
+            .collect();
+        let fallers: Vec<_> = deltas
+            .iter()
+            .rev()
+            .take(limit)
+            .filter(|(_, from, to)| to < from)
+            .copied()
+            .collect();
+
+        ranked.push((network, kendall_tau, [risers, fallers]));
+    }
+
+    // the referrers of all movers are fetched at once, each fetch has to scan all links
+    let mover_ids: Vec<i32> = ranked
+        .iter()
+        .flat_map(|(_, _, moved)| moved.iter().flatten().map(|(domain_id, _, _)| *domain_id))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut referrers: HashMap<i32, Vec<(i32, i64, i64)>> = HashMap::new();
+    for (target_id, source_id, links, total) in DomainLink::referrers(pool, &mover_ids).await? {
+        referrers
+            .entry(target_id)
+            .or_default()
+            .push((source_id, links, total));
+    }
+
+    let mut networks = vec![];
+    for (network, kendall_tau, moved) in ranked {
+        let index = !network as usize;
+        let changed_seeds = changed_seeds(&from_seeds, &to_seeds, network);
+
+        let movers: Vec<Vec<UnresolvedMover>> = moved
+            .into_iter()
+            .map(|moved| {
+                moved
+                    .into_iter()
+                    .map(|(domain_id, from, to)| {
+                        let causes = causes(
+                            domain_id,
+                            index,
+                            referrers.get(&domain_id).map_or(&[][..], Vec::as_slice),
+                            &from_scores,
+                            &to_scores,
+                            &changed_seeds,
+                        );
+                        (domain_id, from, to, causes)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let fqdns = SimpleDomain::fqdns(pool, &domain_ids(&movers)).await?;
+        let mut movers = movers.into_iter().map(|moved| {
+            moved
+                .into_iter()
+                .map(|(domain_id, from, to, causes)| Mover {
+                    domain_id,
+                    fqdn: fqdns.get(&domain_id).cloned().unwrap_or_default(),
+                    from,
+                    to,
+                    delta: to - from,
+                    causes: causes
+                        .into_iter()
+                        .map(|cause| match cause {
+                            Cause::Referrer {
+                                domain_id,
+                                contribution_delta,
+                                seed_changed,
+                                ..
+                            } => Cause::Referrer {
+                                domain_id,
+                                fqdn: fqdns.get(&domain_id).cloned().unwrap_or_default(),
+                                contribution_delta,
+                                seed_changed,
+                            },
+                            cause => cause,
+                        })
+                        .collect(),
+                })
+                .collect()
+        });
+
+        networks.push(NetworkDiff {
+            network,
+            kendall_tau,
+            risers: movers.next().unwrap_or_default(),
+            fallers: movers.next().unwrap_or_default(),
+        });
+    }
+
+    Ok(DiffReport {
+        from_run: from,
+        to_run: to,
+        networks,
+    })
+}
+
+fn scores_by_domain(scores: Vec<DomainScore>) -> HashMap<i32, [f32; 2]> {
+    scores
+        .into_iter()
+        .map(|score| (score.domain_id, score.score))
+        .collect()
+}
+
+/// Seeds of the network that were added (`true`) or removed (`false`) between both runs
+fn changed_seeds(from: &[Seed], to: &[Seed], network: bool) -> HashMap<i32, bool> {
+    let ids = |seeds: &[Seed]| -> HashSet<i32> {
+        seeds
+            .iter()
+            .filter(|seed| seed.network == network)
+            .map(|seed| seed.domain_id)
+            .collect()
+    };
+    let (from, to) = (ids(from), ids(to));
+
+    to.difference(&from)
+        .map(|id| (*id, true))
+        .chain(from.difference(&to).map(|id| (*id, false)))
+        .collect()
+}
+
+/// Causes of the score change of a domain, `referrers` are its `(source_id, links, total)` as returned by
+/// [`DomainLink::referrers`]
+fn causes(
+    domain_id: i32,
+    index: usize,
+    referrers: &[(i32, i64, i64)],
+    from_scores: &HashMap<i32, [f32; 2]>,
+    to_scores: &HashMap<i32, [f32; 2]>,
+    changed_seeds: &HashMap<i32, bool>,
+) -> Vec<Cause> {
+    let mut causes = vec![];
+
+    match changed_seeds.get(&domain_id) {
+        Some(true) => causes.push(Cause::SeedAdded),
+        Some(false) => causes.push(Cause::SeedRemoved),
+        None => (),
+    }
+
+    let mut referrers: Vec<Cause> = referrers
+        .iter()
+        .map(|(referrer, links, total)| {
+            let weight = *links as f32 / *total as f32;
+            let score = |scores: &HashMap<i32, [f32; 2]>| {
+                scores.get(referrer).map_or(0.0, |score| score[index])
+            };
+
+            Cause::Referrer {
+                domain_id: *referrer,
+                fqdn: String::new(),
+                contribution_delta: (score(to_scores) - score(from_scores)) * weight,
+                seed_changed: changed_seeds.contains_key(referrer),
+            }
+        })
+        .filter(|cause| matches!(cause, Cause::Referrer { contribution_delta, .. } if *contribution_delta != 0.0))
+        .collect();
+
+    referrers.sort_by(|a, b| {
+        let magnitude = |cause: &Cause| match cause {
+            Cause::Referrer {
+                contribution_delta, ..
+            } => contribution_delta.abs(),
+            _ => 0.0,
+        };
+        magnitude(b)
+            .partial_cmp(&magnitude(a))
+            .unwrap_or(Ordering::Equal)
+    });
+    referrers.truncate(MAX_REFERRER_CAUSES);
+    causes.extend(referrers);
+
+    causes
+}
+
+fn domain_ids(movers: &[Vec<UnresolvedMover>]) -> Vec<i32> {
+    let mut ids = vec![];
+
+    for (domain_id, _, _, causes) in movers.iter().flatten() {
+        ids.push(*domain_id);
+        for cause in causes {
+            if let Cause::Referrer { domain_id, .. } = cause {
+                ids.push(*domain_id);
+            }
+        }
+    }
+
+    ids
+}
+
+/// Kendall rank correlation (tau-b) with Knight's O(n log n) algorithm
+pub fn kendall_tau(pairs: &mut [(f32, f32)]) -> f64 {
+    let n = pairs.len() as u64;
+    if n < 2 {
+        return 1.0;
+    }
+
+    let compare = |a: f32, b: f32| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    pairs.sort_by(|a, b| compare(a.0, b.0).then(compare(a.1, b.1)));
+
+    // ties in the first ranking and joint ties in both
+    let mut x_ties = 0;
+    let mut joint_ties = 0;
+    let (mut x_run, mut joint_run) = (1, 1);
+    for i in 1..pairs.len() {
+        if pairs[i].0 == pairs[i - 1].0 {
+            x_run += 1;
+            if pairs[i].1 == pairs[i - 1].1 {
+                joint_run += 1;
+            } else {
+                joint_ties += joint_run * (joint_run - 1) / 2;
+                joint_run = 1;
+            }
+        } else {
+            x_ties += x_run * (x_run - 1) / 2;
+            joint_ties += joint_run * (joint_run - 1) / 2;
+            x_run = 1;
+            joint_run = 1;
+        }
+    }
+    x_ties += x_run * (x_run - 1) / 2;
+    joint_ties += joint_run * (joint_run - 1) / 2;
+
+    // every swap of the merge sort on the second ranking is a discordant pair
+    let mut second: Vec<f32> = pairs.iter().map(|pair| pair.1).collect();
+    let mut buffer = second.clone();
+    let swaps = merge_count(&mut second, &mut buffer);
+
+    let mut y_ties = 0;
+    let mut y_run = 1;
+    for i in 1..second.len() {
+        if second[i] == second[i - 1] {
+            y_run += 1;
+        } else {
+            y_ties += y_run * (y_run - 1) / 2;
+            y_run = 1;
+        }
+    }
+    y_ties += y_run * (y_run - 1) / 2;
+
+    let pairs_total = n * (n - 1) / 2;
+    let denominator = ((pairs_total - x_ties) as f64 * (pairs_total - y_ties) as f64).sqrt();
+    if denominator == 0.0 {
+        return 1.0;
+    }
+
+    (pairs_total as f64 - x_ties as f64 - y_ties as f64 + joint_ties as f64 - 2.0 * swaps as f64)
+        / denominator
+}
+
+/// Merge sort `values` and return the count of swapped pairs
+pub fn merge_count(values: &mut [f32], buffer: &mut [f32]) -> u64 {
+    let len = values.len();
+    if len < 2 {
+        return 0;
+    }
+
+    let middle = len / 2;
+    let mut swaps = merge_count(&mut values[..middle], &mut buffer[..middle])
+        + merge_count(&mut values[middle..], &mut buffer[middle..]);
+
+    let (mut left, mut right, mut out) = (0, middle, 0);
+    while left < middle && right < len {
+        if values[right] < values[left] {
+            buffer[out] = values[right];
+            swaps += (middle - left) as u64;
+            right += 1;
+        } else {
+            buffer[out] = values[left];
+            left += 1;
+        }
+        out += 1;
+    }
+    buffer[out..out + middle - left].copy_from_slice(&values[left..middle]);
+    out += middle - left;
+    buffer[out..len].copy_from_slice(&values[right..len]);
+
+    values.copy_from_slice(&buffer[..len]);
+    swaps
+}
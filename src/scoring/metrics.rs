@@ -0,0 +1,54 @@
+use super::runner::RunHandle;
+use crate::core::config::CONFIG;
+use crate::db::models::ScoreRunState;
+use lazy_static::lazy_static;
+use opentelemetry::{
+    global,
+    metrics::{Counter, ValueRecorder},
+    KeyValue,
+};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+lazy_static! {
+    static ref RUN_DURATION: ValueRecorder<f64> = global::meter(&CONFIG.tracing.meter)
+        .f64_value_recorder("scoring.run.duration")
+        .with_description("Duration of scoring runs in seconds")
+        .init();
+    static ref RUNS: Counter<u64> = global::meter(&CONFIG.tracing.meter)
+        .u64_counter("scoring.runs")
+        .with_description("Count of completed scoring runs by their final state")
+        .init();
+}
+
+/// Record duration and final state of a completed run
+pub fn record_run(state: ScoreRunState, duration: Duration) {
+    let attributes = [KeyValue::new("state", state.as_str())];
+
+    RUN_DURATION.record(duration.as_secs_f64(), &attributes);
+    RUNS.add(1, &attributes);
+}
+
+/// Report the progress of the current run on every metrics collection
+pub fn observe_progress(current: Arc<Mutex<Option<RunHandle>>>) {
+    global::meter(&CONFIG.tracing.meter)
+        .u64_value_observer("scoring.run.progress", move |result| {
+            // runs are only observed once they were created
+            if let Some((run_id, handle)) = current
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|handle| Some((handle.run_id?, handle)))
+            {
+                let attributes = [KeyValue::new("run", run_id as i64)];
+
+                result.observe(
+                    handle.progress.processed.load(Ordering::Relaxed) as u64,
+                    &attributes,
+                );
+            }
+        })
+        .with_description("Count of domains visited by the current scoring run")
+        .init();
+}
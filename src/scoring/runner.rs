@@ -0,0 +1,250 @@
+use super::metrics;
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::db::models::{DomainLink, DomainScore, ScoreRun, ScoreRunState, Seed};
+use crate::db::util::DbPool;
+use crate::domains::graph::acg::ACG;
+use crate::domains::graph::csr::{CSRGraph, Progress};
+use crate::domains::graph::vertex::{DomainVertex, Vertex};
+use crate::domains::prefixes;
+use actix_web::web::Data;
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Handle of the run currently executed by the [`ScoringRunner`]
+#[derive(Debug, Clone)]
+pub struct RunHandle {
+    /// `None` while the run is created
+    pub run_id: Option<i32>,
+    pub progress: Arc<Progress>,
+}
+
+/// Executes scoring runs on a dedicated worker pool, off the request path.
+/// Only a single run may be executed at a time.
+pub struct ScoringRunner {
+    current: Arc<Mutex<Option<RunHandle>>>,
+}
+
+impl ScoringRunner {
+    pub fn new() -> Self {
+        let current = Arc::new(Mutex::new(None));
+        metrics::observe_progress(current.clone());
+
+        Self { current }
+    }
+
+    /// Start a new scoring run in the background. The returned run is still in the `running` state
+    pub async fn start(runner: Data<ScoringRunner>, pool: DbPool) -> Result<ScoreRun, APIError> {
+        let progress = Arc::new(Progress::default());
+
+        // reserve the runner before creating the run to avoid two concurrent runs
+        {
+            let mut current = runner.current.lock().unwrap();
+            match current.as_ref().map(|handle| handle.run_id) {
+                Some(Some(run_id)) => {
+                    return Err(APIError::ConflictError(format!(
+                        "Scoring run {} is still in progress",
+                        run_id
+                    )))
+                }
+                Some(None) => {
+                    return Err(APIError::ConflictError(
+                        "A scoring run is being started".to_owned(),
+                    ))
+                }
+                None => {}
+            }
+            *current = Some(RunHandle {
+                run_id: None,
+                progress: progress.clone(),
+            });
+        }
+
+        let run = match ScoreRun::create(&pool).await {
+            Ok(run) => run,
+            Err(e) => {
+                runner.current.lock().unwrap().take();
+                return Err(e);
+            }
+        };
+
+        if let Some(handle) = runner.current.lock().unwrap().as_mut() {
+            handle.run_id = Some(run.id);
+        }
+
+        actix_web::rt::spawn(Self::execute(runner, pool, run.clone(), progress));
+
+        Ok(run)
+    }
+
+    /// Current run, if any
+    pub fn current(&self) -> Option<RunHandle> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Request the cancellation of a running run. The run stops before propagating the next layer
+    pub fn cancel(&self, run_id: i32) -> Result<(), APIError> {
+        match self.current.lock().unwrap().as_ref() {
+            Some(handle) if handle.run_id == Some(run_id) => {
+                handle.progress.cancelled.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            _ => Err(APIError::NotFoundError),
+        }
+    }
+
+    async fn execute(
+        runner: Data<ScoringRunner>,
+        pool: DbPool,
+        run: ScoreRun,
+        progress: Arc<Progress>,
+    ) {
+        let started = Instant::now();
+        let heartbeat = actix_web::rt::spawn(Self::heartbeat(pool.clone(), run.clone()));
+
+        let state = match Self::score(&pool, &run, progress).await {
+            Ok(true) => ScoreRunState::Finished,
+            Ok(false) => ScoreRunState::Cancelled,
+            Err(e) => {
+                tracing::error!("Scoring run {} failed: {}", run.id, String::from(e));
+                ScoreRunState::Failed
+            }
+        };
+        heartbeat.abort();
+
+        if let Err(e) = run.finish(&pool, state).await {
+            tracing::error!(
+                "Failed to store state of scoring run {}: {}",
+                run.id,
+                String::from(e)
+            );
+        }
+
+        metrics::record_run(state, started.elapsed());
+        tracing::info!(
+            "Scoring run {} {} after {:?}",
+            run.id,
+            state.as_str(),
+            started.elapsed()
+        );
+
+        runner.current.lock().unwrap().take();
+    }
+
+    /// Store the heartbeat of a run until the task is aborted
+    async fn heartbeat(pool: DbPool, run: ScoreRun) {
+        let interval = Duration::from_secs(CONFIG.scoring.heartbeat);
+
+        loop {
+            actix_web::rt::time::sleep(interval).await;
+            if let Err(e) = run.heartbeat(&pool).await {
+                tracing::warn!(
+                    "Failed to store the heartbeat of scoring run {}: {}",
+                    run.id,
+                    String::from(e)
+                );
+            }
+        }
+    }
+
+    /// Score all domains for both networks. Returns `false` if the run was cancelled
+    async fn score(
+        pool: &DbPool,
+        run: &ScoreRun,
+        progress: Arc<Progress>,
+    ) -> Result<bool, APIError> {
+        let edges = DomainLink::canonical_edges(pool).await?;
+        let seeds = Seed::all(pool).await?;
+        Seed::snapshot(pool, run.id, &seeds).await?;
+
+        let (sender, receiver) = oneshot::channel();
+
+        // the propagation is CPU bound and may not block the actix runtime
+        thread::Builder::new()
+            .name(format!("scoring-{}", run.id))
+            .spawn(move || {
+                let _ = sender.send(Self::propagate(
+                    &edges,
+                    &seeds,
+                    CONFIG.scoring.root_value,
+                    CONFIG.scoring.workers,
+                    &progress,
+                ));
+            })
+            .map_err(|e| APIError::InternalError(e.to_string()))?;
+
+        match receiver.await {
+            Ok(Some(scores)) => {
+                DomainScore::insert_many(pool, run.id, &scores).await?;
+                prefixes::publish(pool, run.id).await?;
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(_) => Err(APIError::InternalError(
+                "Scoring worker stopped unexpectedly".to_owned(),
+            )),
+        }
+    }
+
+    /// Score the graph for both networks. Links closing a cycle are removed with a depth first
+    /// search from the seeds of each network, otherwise the domains on the cycle would never be
+    /// visited. Returns `None` if the run was cancelled
+    pub fn propagate(
+        edges: &[(i32, i32, u32)],
+        seeds: &[Seed],
+        root_value: f32,
+        workers: usize,
+        progress: &Progress,
+    ) -> Option<Vec<DomainScore>> {
+        let mut scores: HashMap<i32, [f32; 2]> = HashMap::new();
+
+        for network in [true, false] {
+            let roots: Vec<DomainVertex> = seeds
+                .iter()
+                .filter(|seed| seed.network == network)
+                .map(|seed| DomainVertex::Simple(Vertex { id: seed.domain_id }))
+                .collect();
+
+            // the cycles removed depend on the roots, so every network is decycled on its own
+            let mut acg = ACG::from_edges(edges);
+            acg.decycle(roots.clone());
+            let graph = CSRGraph::from(&acg);
+            drop(acg);
+
+            if network {
+                progress
+                    .total
+                    .store(graph.node_count() * 2, Ordering::Relaxed);
+            }
+
+            let roots: Vec<i32> = roots.iter().map(DomainVertex::id).collect();
+            let network_scores = graph.propagate_parallel(&roots, root_value, workers, progress)?;
+
+            for (id, score) in graph.ids.iter().zip(network_scores) {
+                if score > 0.0 {
+                    scores.entry(*id).or_insert([0.0; 2])[!network as usize] = score;
+                }
+            }
+        }
+
+        // seeds without any links aren't part of the graph but keep their root value
+        for seed in seeds {
+            let score =
+                &mut scores.entry(seed.domain_id).or_insert([0.0; 2])[!seed.network as usize];
+            if *score == 0.0 {
+                *score = root_value;
+            }
+        }
+
+        Some(
+            scores
+                .into_iter()
+                .map(|(domain_id, score)| DomainScore { domain_id, score })
+                .collect(),
+        )
+    }
+}
@@ -1 +1,9 @@
-
+mod admin;
+mod cursor;
+mod diff;
+mod graph;
+mod import;
+mod limits;
+mod prefixes;
+mod propagation;
+mod upsert;
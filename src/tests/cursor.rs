@@ -0,0 +1,47 @@
+//! Keyset pagination cursors handed out to clients
+
+use crate::db::util::Cursor;
+
+#[test]
+fn cursor_round_trip() {
+    for cursor in [
+        Cursor {
+            fqdn_hash: "0a1b2c3d4e5f".to_owned(),
+            id: 42,
+        },
+        Cursor {
+            fqdn_hash: "f".to_owned(),
+            id: 0,
+        },
+        Cursor {
+            fqdn_hash: "abcdef0123456789".to_owned(),
+            id: i32::MAX,
+        },
+    ] {
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+    }
+}
+
+#[test]
+fn cursor_encodes_id_as_fixed_width_suffix() {
+    let cursor = Cursor {
+        fqdn_hash: "abc".to_owned(),
+        id: 255,
+    };
+
+    assert_eq!(cursor.encode(), "abc000000ff");
+}
+
+#[test]
+fn malformed_cursors_are_rejected() {
+    for cursor in [
+        "",
+        // only an id without a hash
+        "000000ff",
+        "abc0000xyz",
+        "abc-000000ff",
+        "äbc000000ff",
+    ] {
+        assert_eq!(Cursor::decode(cursor), None, "{:?} was decoded", cursor);
+    }
+}
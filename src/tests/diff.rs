@@ -0,0 +1,99 @@
+//! Rank correlation of the score diff report, compared against a brute force tau-b
+
+use crate::scoring::diff::{kendall_tau, merge_count};
+use rand::Rng;
+
+fn assert_tau(pairs: &[(f32, f32)], expected: f64) {
+    let tau = kendall_tau(&mut pairs.to_vec());
+    assert!(
+        (tau - expected).abs() < 1e-9,
+        "tau is {} instead of {}",
+        tau,
+        expected
+    );
+}
+
+/// O(n²) tau-b, counting every pair on its own
+fn brute_force_tau(pairs: &[(f32, f32)]) -> f64 {
+    let (mut concordant, mut discordant, mut x_ties, mut y_ties) = (0.0, 0.0, 0.0, 0.0);
+    for (i, a) in pairs.iter().enumerate() {
+        for b in &pairs[i + 1..] {
+            let product = (a.0 - b.0) * (a.1 - b.1);
+            if a.0 == b.0 {
+                x_ties += 1.0;
+            }
+            if a.1 == b.1 {
+                y_ties += 1.0;
+            }
+            if product > 0.0 {
+                concordant += 1.0;
+            } else if product < 0.0 {
+                discordant += 1.0;
+            }
+        }
+    }
+
+    let total = (pairs.len() * pairs.len().saturating_sub(1) / 2) as f64;
+    let denominator = ((total - x_ties) * (total - y_ties)).sqrt();
+    if denominator == 0.0 {
+        1.0
+    } else {
+        (concordant - discordant) / denominator
+    }
+}
+
+#[test]
+fn merge_count_counts_swaps() {
+    for (values, swaps) in [
+        (vec![1.0, 2.0, 3.0], 0),
+        (vec![3.0, 1.0, 2.0], 2),
+        (vec![4.0, 3.0, 2.0, 1.0], 6),
+        // equal values are never swapped
+        (vec![1.0, 2.0, 2.0, 1.0], 2),
+    ] {
+        let mut sorted = values.clone();
+        let mut buffer = values.clone();
+
+        assert_eq!(merge_count(&mut sorted, &mut buffer), swaps);
+        assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
+
+#[test]
+fn tau_without_ties() {
+    assert_tau(&[(1.0, 1.0), (2.0, 2.0), (3.0, 3.0)], 1.0);
+    assert_tau(&[(1.0, 3.0), (2.0, 2.0), (3.0, 1.0)], -1.0);
+    assert_tau(&[(1.0, 1.0), (2.0, 3.0), (3.0, 2.0)], 1.0 / 3.0);
+}
+
+#[test]
+fn tau_b_with_ties() {
+    // ties in only one of the rankings
+    assert_tau(&[(1.0, 1.0), (2.0, 2.0), (2.0, 3.0), (3.0, 3.0)], 0.8);
+    // joint ties, e.g. domains unscored in both runs
+    assert_tau(&[(0.0, 0.0), (0.0, 0.0), (1.0, 2.0), (2.0, 1.0)], 0.6);
+    assert_tau(
+        &[(1.0, 3.0), (2.0, 1.0), (3.0, 2.0), (3.0, 2.0), (0.0, 2.0)],
+        -0.1259881576697424,
+    );
+}
+
+#[test]
+fn tau_of_constant_rankings() {
+    assert_tau(&[], 1.0);
+    assert_tau(&[(1.0, 2.0)], 1.0);
+    assert_tau(&[(1.0, 1.0), (1.0, 1.0)], 1.0);
+}
+
+#[test]
+fn tau_matches_brute_force() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..200 {
+        // few distinct scores, so most inputs contain ties
+        let pairs: Vec<(f32, f32)> = (0..rng.gen_range(0..40))
+            .map(|_| (rng.gen_range(0..6) as f32, rng.gen_range(0..6) as f32))
+            .collect();
+
+        assert_tau(&pairs, brute_force_tau(&pairs));
+    }
+}
@@ -0,0 +1,119 @@
+//! Benchmarks comparing the mutable [`ACG`] with the read-only [`CSRGraph`].
+//! They are ignored by default, run them with
+//! `cargo test --release -- --ignored --nocapture bench_`
+
+use crate::domains::graph::{
+    acg::ACG,
+    csr::{CSRGraph, Progress},
+    vertex::{DomainVertex, Vertex},
+};
+use std::time::Instant;
+
+const LAYERS: i32 = 20;
+const LAYER_WIDTH: i32 = 10_000;
+const LINKS_PER_DOMAIN: i32 = 10;
+
+fn vertex(id: i32) -> DomainVertex {
+    DomainVertex::Simple(Vertex { id })
+}
+
+/// Layered, acyclic graph where every domain links to domains of the following layer
+fn layered_edges() -> Vec<(i32, i32, u32)> {
+    // xorshift to keep the benchmark deterministic without pulling in rand
+    let mut state: u32 = 0x9E37_79B9;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    let mut edges = vec![];
+    for layer in 0..LAYERS - 1 {
+        for offset in 0..LAYER_WIDTH {
+            let source = layer * LAYER_WIDTH + offset;
+            let mut targets: Vec<i32> = (0..LINKS_PER_DOMAIN)
+                .map(|_| (layer + 1) * LAYER_WIDTH + (next() % LAYER_WIDTH as u32) as i32)
+                .collect();
+            targets.sort_unstable();
+            targets.dedup();
+
+            for target in targets {
+                edges.push((source, target, next() % 4 + 1));
+            }
+        }
+    }
+    edges
+}
+
+fn build_acg(edges: &[(i32, i32, u32)]) -> ACG {
+    let mut acg = ACG::new();
+    for id in 0..LAYERS * LAYER_WIDTH {
+        acg.add_domain(vertex(id)).unwrap();
+    }
+    for (source, target, count) in edges {
+        acg.link(&vertex(*source), &vertex(*target), *count)
+            .unwrap();
+    }
+    acg
+}
+
+fn roots() -> Vec<i32> {
+    (0..LAYER_WIDTH).step_by(100).collect()
+}
+
+#[test]
+#[ignore]
+fn bench_memory_usage() {
+    let edges = layered_edges();
+    let acg = build_acg(&edges);
+    let csr = CSRGraph::from(&acg);
+
+    println!(
+        "{} domains, {} links: ACG {} KiB, CSR {} KiB",
+        csr.node_count(),
+        csr.edge_count(),
+        acg.memory_usage() / 1024,
+        csr.memory_usage() / 1024
+    );
+    assert!(csr.memory_usage() < acg.memory_usage());
+}
+
+#[test]
+#[ignore]
+fn bench_propagation() {
+    let edges = layered_edges();
+    let acg = build_acg(&edges);
+    let csr = CSRGraph::from_edges(&edges);
+    let roots = roots();
+    let root_vertices: Vec<DomainVertex> = roots.iter().map(|id| vertex(*id)).collect();
+
+    let start = Instant::now();
+    let acg_scores = acg.propagate(&root_vertices, 1.0);
+    let acg_duration = start.elapsed();
+
+    let start = Instant::now();
+    let csr_scores = csr.propagate(&roots, 1.0);
+    let csr_duration = start.elapsed();
+
+    let start = Instant::now();
+    let parallel_scores = csr
+        .propagate_parallel(&roots, 1.0, 4, &Progress::default())
+        .unwrap();
+    let parallel_duration = start.elapsed();
+
+    println!(
+        "propagation over {} links: ACG {:?}, CSR {:?}, CSR with 4 workers {:?}",
+        csr.edge_count(),
+        acg_duration,
+        csr_duration,
+        parallel_duration
+    );
+
+    // both representations have to agree on the resulting scores
+    for (node, id) in csr.ids.iter().enumerate() {
+        let expected = acg_scores.get(id).copied().unwrap_or(0.0);
+        assert!((csr_scores[node] - expected).abs() <= 1e-3 * expected.max(1.0));
+        assert!((parallel_scores[node] - expected).abs() <= 1e-3 * expected.max(1.0));
+    }
+}
@@ -0,0 +1,75 @@
+//! Parsing of offline import files and the rows loaded with `COPY`
+
+use crate::commands::import::{parse, parse_csv, Format};
+use crate::core::errors::APIError;
+use crate::db::models::copy_row;
+
+#[test]
+fn csv_header_is_skipped() {
+    let header = "fqdn,network,last_updated,link";
+
+    assert!(parse(&Format::Csv, header, 1).unwrap().is_none());
+    // only the first line may be a header
+    assert!(parse(&Format::Csv, header, 2).is_err());
+}
+
+#[test]
+fn csv_rows_without_link() {
+    let record = parse_csv("example.com,true,1639000000").unwrap();
+
+    assert_eq!(record.fqdn, "example.com");
+    assert!(record.network);
+    assert_eq!(record.last_updated, 1639000000);
+    assert!(record.links.is_empty());
+    assert_eq!(record.canonical, None);
+}
+
+#[test]
+fn csv_rows_with_link() {
+    let record = parse_csv(" example.com , false , 1639000000 , example.org ").unwrap();
+
+    assert_eq!(record.fqdn, "example.com");
+    assert!(!record.network);
+    assert_eq!(record.links, vec!["example.org".to_owned()]);
+
+    // an empty link column is the same as no link
+    assert!(parse_csv("example.com,false,1639000000,")
+        .unwrap()
+        .links
+        .is_empty());
+}
+
+#[test]
+fn csv_rows_with_invalid_columns() {
+    for (line, field) in [
+        ("example.com,true", "line"),
+        (
+            "example.com,true,1639000000,example.org,example.net",
+            "line",
+        ),
+        ("example.com,yes,1639000000", "network"),
+        ("example.com,true,yesterday", "last_updated"),
+    ] {
+        match parse_csv(line) {
+            Err(APIError::ValidationError([loc], _)) => assert_eq!(loc, field, "{}", line),
+            _ => panic!("{} was parsed", line),
+        }
+    }
+}
+
+#[test]
+fn copy_row_separates_columns() {
+    let mut buffer = String::new();
+    copy_row(&mut buffer, &["0", "example.com", "t"]);
+    copy_row(&mut buffer, &["1", "", "f"]);
+
+    assert_eq!(buffer, "0\texample.com\tt\n1\t\tf\n");
+}
+
+#[test]
+fn copy_row_escapes_delimiters() {
+    let mut buffer = String::new();
+    copy_row(&mut buffer, &["back\\slash", "tab\there", "line\nbreak\r"]);
+
+    assert_eq!(buffer, "back\\\\slash\ttab\\there\tline\\nbreak\\r\n");
+}
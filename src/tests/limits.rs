@@ -0,0 +1,96 @@
+//! Configurable limits of submitted updates and errors of malformed bodies
+
+use crate::core::config::CONFIG;
+use crate::core::errors::APIError;
+use crate::core::limits::check_length;
+use crate::domains::util::parse_update;
+
+fn update(fqdn: &str, links: usize, canonical: Option<&str>) -> String {
+    serde_json::json!({
+        "fqdn": fqdn,
+        "network": true,
+        "links": (0..links).map(|link| format!("link{}.example.com", link)).collect::<Vec<_>>(),
+        "last_updated": 1639000000,
+        "canonical": canonical,
+    })
+    .to_string()
+}
+
+fn rejected_field(payload: &str) -> Option<&'static str> {
+    match parse_update(payload.as_bytes()) {
+        Err(APIError::ValidationError([field], _)) => Some(field),
+        _ => None,
+    }
+}
+
+#[test]
+fn lengths_up_to_the_limit_are_accepted() {
+    assert!(check_length("title", "", 0).is_ok());
+    assert!(check_length("title", "abc", 3).is_ok());
+}
+
+#[test]
+fn lengths_are_counted_in_bytes() {
+    // two characters, four bytes
+    assert!(check_length("title", "üü", 3).is_err());
+}
+
+#[test]
+fn exceeded_limits_name_the_field() {
+    match check_length("medium", "abcd", 3) {
+        Err(APIError::ValidationError(["medium"], msg)) => {
+            assert_eq!(msg, "medium exceeds the limit of 3 bytes")
+        }
+        _ => panic!("the length wasn't rejected"),
+    }
+}
+
+#[test]
+fn updates_within_the_limits_are_accepted() {
+    let limits = &CONFIG.limits;
+
+    assert!(
+        parse_update(update("example.com", limits.links, Some("example.org")).as_bytes()).is_ok()
+    );
+}
+
+#[test]
+fn updates_exceeding_a_limit_are_rejected_by_validation() {
+    let limits = &CONFIG.limits;
+    let long = format!("{}.com", "a".repeat(limits.fqdn_length));
+
+    assert_eq!(rejected_field(&update(&long, 1, None)), Some("fqdn"));
+    assert_eq!(
+        rejected_field(&update("example.com", limits.links + 1, None)),
+        Some("links")
+    );
+    assert_eq!(
+        rejected_field(&update("example.com", 1, Some(&long))),
+        Some("canonical")
+    );
+}
+
+#[test]
+fn malformed_bodies_are_reported_without_position() {
+    match parse_update(br#"{"fqdn": "example.com", "network": "yes"}"#) {
+        Err(APIError::ValidationError(["body"], msg)) => {
+            assert!(msg.starts_with("invalid type"), "{}", msg);
+            assert!(!msg.contains(" at line "), "{}", msg);
+        }
+        _ => panic!("the body wasn't rejected"),
+    }
+}
+
+#[test]
+fn updates_with_an_invalid_fqdn_are_rejected() {
+    assert_eq!(rejected_field(&update("localhost", 0, None)), Some("fqdn"));
+    assert_eq!(
+        rejected_field(&update("example.123", 0, None)),
+        Some("fqdn")
+    );
+    assert_eq!(
+        rejected_field(&update("://example.com", 0, None)),
+        Some("fqdn")
+    );
+    assert!(parse_update(update("example.com.", 0, None).as_bytes()).is_ok());
+}
@@ -0,0 +1,23 @@
+//! Handling of the hash prefixes sent by clients
+
+use crate::domains::util::collapse_prefixes;
+
+fn prefixes(prefixes: &[&str]) -> Vec<String> {
+    prefixes.iter().map(|prefix| prefix.to_string()).collect()
+}
+
+#[test]
+fn collapse_drops_covered_prefixes() {
+    assert_eq!(
+        collapse_prefixes(prefixes(&["abcd", "ab12", "abc", "ab", "f00", "abc"])),
+        prefixes(&["ab", "f00"])
+    );
+}
+
+#[test]
+fn collapse_keeps_disjoint_prefixes() {
+    assert_eq!(
+        collapse_prefixes(prefixes(&["abd", "abc", "abce", "ab0"])),
+        prefixes(&["ab0", "abc", "abd"])
+    );
+}
@@ -0,0 +1,194 @@
+//! Tri-color propagation over small graphs with known scores
+
+use crate::db::models::Seed;
+use crate::domains::graph::{
+    acg::ACG,
+    csr::{CSRGraph, Progress},
+    vertex::{DomainVertex, Vertex},
+};
+use crate::scoring::runner::ScoringRunner;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+fn vertex(id: i32) -> DomainVertex {
+    DomainVertex::Simple(Vertex { id })
+}
+
+fn graph(domains: &[i32], edges: &[(i32, i32, u32)]) -> ACG {
+    let mut acg = ACG::new();
+    for id in domains {
+        acg.add_domain(vertex(*id)).unwrap();
+    }
+    for (source, target, count) in edges {
+        acg.link(&vertex(*source), &vertex(*target), *count)
+            .unwrap();
+    }
+    acg
+}
+
+fn score(acg: &mut ACG, roots: &[i32]) -> HashMap<i32, f32> {
+    let roots: Vec<DomainVertex> = roots.iter().map(|id| vertex(*id)).collect();
+    acg.decycle(roots.clone());
+    acg.propagate(&roots, 1.0)
+}
+
+fn assert_scores(scores: &HashMap<i32, f32>, expected: &[(i32, f32)]) {
+    for (id, score) in expected {
+        assert!(
+            (scores[id] - score).abs() < 1e-6,
+            "domain {} has score {} instead of {}",
+            id,
+            scores[id],
+            score
+        );
+    }
+}
+
+#[test]
+fn chain() {
+    let mut acg = graph(&[1, 2, 3], &[(1, 2, 1), (2, 3, 1)]);
+
+    assert_scores(&score(&mut acg, &[1]), &[(1, 1.0), (2, 1.0), (3, 1.0)]);
+}
+
+#[test]
+fn weighted_diamond() {
+    let mut acg = graph(&[1, 2, 3, 4], &[(1, 2, 1), (1, 3, 3), (2, 4, 1), (3, 4, 1)]);
+
+    assert_scores(
+        &score(&mut acg, &[1]),
+        &[(1, 1.0), (2, 0.25), (3, 0.75), (4, 1.0)],
+    );
+}
+
+#[test]
+fn multiple_roots() {
+    let mut acg = graph(&[1, 2, 3], &[(1, 3, 1), (2, 3, 1), (2, 1, 1)]);
+
+    // 2 passes half of its value to 1, which is only visited afterwards
+    assert_scores(&score(&mut acg, &[1, 2]), &[(1, 1.5), (2, 1.0), (3, 2.0)]);
+}
+
+#[test]
+fn cycle_is_removed() {
+    let mut acg = graph(&[1, 2, 3], &[(1, 2, 1), (2, 3, 1), (3, 2, 1), (3, 1, 1)]);
+    let scores = score(&mut acg, &[1]);
+
+    // both links back to 2 and 1 close a cycle
+    assert!(acg.get_domain(&vertex(3)).unwrap().is_empty());
+    assert_scores(&scores, &[(1, 1.0), (2, 1.0), (3, 1.0)]);
+}
+
+#[test]
+fn unreachable_domains() {
+    // 4 and 5 form a cycle that can't be reached from the root but links to 3
+    let mut acg = graph(
+        &[1, 2, 3, 4, 5],
+        &[(1, 2, 1), (2, 3, 1), (4, 5, 1), (5, 4, 1), (5, 3, 1)],
+    );
+
+    assert_scores(
+        &score(&mut acg, &[1]),
+        &[(1, 1.0), (2, 1.0), (3, 1.0), (4, 0.0), (5, 0.0)],
+    );
+}
+
+#[test]
+fn undecycled_cycles_stay_unmarked() {
+    let acg = graph(&[1, 2, 3, 4], &[(1, 2, 1), (2, 3, 1), (3, 2, 1), (3, 4, 1)]);
+    let scores = acg.propagate(&[vertex(1)], 1.0);
+
+    // 2 receives the score of 1 but never gets marked, so 3 and 4 stay without score
+    assert_scores(&scores, &[(1, 1.0), (2, 1.0), (3, 0.0), (4, 0.0)]);
+}
+
+#[test]
+fn csr_matches_acg() {
+    let edges = [
+        (1, 2, 2),
+        (1, 3, 1),
+        (2, 4, 1),
+        (3, 4, 4),
+        (3, 5, 1),
+        (4, 5, 1),
+        (6, 5, 1),
+    ];
+    let mut acg = graph(&[1, 2, 3, 4, 5, 6], &edges);
+    let scores = score(&mut acg, &[1, 6]);
+    let csr = CSRGraph::from(&acg);
+
+    for (node, score) in csr.propagate(&[1, 6], 1.0).into_iter().enumerate() {
+        assert!((scores[&csr.ids[node]] - score).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn parallel_propagation_matches_sequential() {
+    // the middle layer is wide enough to be split across the workers
+    let width = 3000;
+    let edges: Vec<(i32, i32, u32)> = (1..=width)
+        .flat_map(|node| {
+            [
+                (0, node, 1),
+                (node, width + 1 + node % 7, (node % 3 + 1) as u32),
+            ]
+        })
+        .collect();
+    let csr = CSRGraph::from_edges(&edges);
+    let expected = csr.propagate(&[0], 1.0);
+
+    for workers in [1, 3, 8] {
+        let progress = Progress::default();
+        let scores = csr
+            .propagate_parallel(&[0], 1.0, workers, &progress)
+            .unwrap();
+
+        assert_eq!(progress.processed.load(Ordering::Relaxed), csr.node_count());
+        for (score, expected) in scores.iter().zip(&expected) {
+            assert!((score - expected).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn cancelled_propagation_has_no_scores() {
+    let csr = CSRGraph::from_edges(&[(1, 2, 1), (2, 3, 1)]);
+    let progress = Progress::default();
+    progress.cancelled.store(true, Ordering::Relaxed);
+
+    assert!(csr.propagate_parallel(&[1], 1.0, 4, &progress).is_none());
+}
+
+#[test]
+fn runner_decycles_from_seeds() {
+    let edges = [(1, 2, 1), (2, 3, 1), (3, 2, 1), (3, 4, 1), (5, 6, 1)];
+    let seeds = [
+        Seed {
+            domain_id: 1,
+            network: true,
+        },
+        Seed {
+            domain_id: 5,
+            network: false,
+        },
+        Seed {
+            domain_id: 7,
+            network: false,
+        },
+    ];
+    let scores: HashMap<i32, [f32; 2]> =
+        ScoringRunner::propagate(&edges, &seeds, 1.0, 1, &Progress::default())
+            .unwrap()
+            .into_iter()
+            .map(|score| (score.domain_id, score.score))
+            .collect();
+
+    // without removing 3 -> 2 the cycle would never be visited and 3 and 4 stay at 0
+    for (id, expected) in [(1, 1.0), (2, 1.0), (3, 1.0), (4, 1.0)] {
+        assert!((scores[&id][0] - expected).abs() < 1e-6);
+        assert_eq!(scores[&id][1], 0.0);
+    }
+    assert_eq!(scores[&6], [0.0, 1.0]);
+    // seeds without links keep their root value
+    assert_eq!(scores[&7], [0.0, 1.0]);
+}